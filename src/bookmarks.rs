@@ -0,0 +1,283 @@
+use percent_encoding::{percent_decode_str, percent_encode, AsciiSet, CONTROLS};
+
+use std::collections::HashSet;
+use std::ffi::OsStr;
+use std::fs;
+use std::io;
+use std::os::unix::ffi::OsStrExt;
+use std::path::{Path, PathBuf};
+
+use crate::utils::{write_atomic, xdg_config_home};
+
+/// What GLib escapes in the path of a `file://` URI.
+const PATH_ENCODE_SET: &AsciiSet = &CONTROLS
+    .add(b' ')
+    .add(b'"')
+    .add(b'#')
+    .add(b'%')
+    .add(b'<')
+    .add(b'>')
+    .add(b'?')
+    .add(b'[')
+    .add(b'\\')
+    .add(b']')
+    .add(b'^')
+    .add(b'`')
+    .add(b'{')
+    .add(b'|')
+    .add(b'}');
+
+pub fn path_to_uri(path: &Path) -> String {
+    format!(
+        "file://{}",
+        percent_encode(path.as_os_str().as_bytes(), PATH_ENCODE_SET)
+    )
+}
+
+pub fn uri_to_path(uri: &str) -> Option<PathBuf> {
+    let path = uri.strip_prefix("file://")?;
+    // a host part, like file://localhost/tmp, is allowed by the URI spec
+    let path = &path[path.find('/')?..];
+    let bytes: Vec<u8> = percent_decode_str(path).collect();
+    Some(PathBuf::from(OsStr::from_bytes(&bytes)))
+}
+
+/// One line of the GTK bookmarks file, `URI[ label]`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Bookmark {
+    uri: String,
+    label: Option<String>,
+}
+
+impl Bookmark {
+    /// `None` for bookmarks of remote locations, like `sftp://`, which are
+    /// kept in the file but cannot be entered.
+    pub fn path(&self) -> Option<PathBuf> {
+        uri_to_path(&self.uri)
+    }
+
+    /// The label written in the file, if any.
+    pub fn custom_label(&self) -> &str {
+        self.label.as_deref().unwrap_or_default()
+    }
+
+    pub fn label(&self) -> String {
+        if let Some(label) = &self.label {
+            return label.clone();
+        }
+        self.path()
+            .and_then(|path| {
+                path.file_name()
+                    .map(|name| name.to_string_lossy().to_string())
+            })
+            .unwrap_or_else(|| self.uri.clone())
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct Bookmarks {
+    entries: Vec<Bookmark>,
+    /// The URIs of the bookmarks renamed since the file was last written,
+    /// whose labels win over the ones read again.
+    renamed: HashSet<String>,
+}
+
+impl Bookmarks {
+    /// Shared with Nautilus and the GTK file dialogs.
+    pub fn path() -> PathBuf {
+        xdg_config_home().join("gtk-3.0/bookmarks")
+    }
+
+    fn parse(content: &str) -> Vec<Bookmark> {
+        content
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .map(|line| match line.split_once(' ') {
+                Some((uri, label)) => Bookmark {
+                    uri: uri.to_string(),
+                    label: Some(label.to_string()).filter(|label| !label.is_empty()),
+                },
+                None => Bookmark {
+                    uri: line.to_string(),
+                    label: None,
+                },
+            })
+            .collect()
+    }
+
+    fn serialize(&self) -> String {
+        self.entries
+            .iter()
+            .map(|bookmark| match &bookmark.label {
+                Some(label) => format!("{} {label}\n", bookmark.uri),
+                None => format!("{}\n", bookmark.uri),
+            })
+            .collect()
+    }
+
+    pub fn load() -> Self {
+        let entries = fs::read_to_string(Self::path())
+            .map(|content| Self::parse(&content))
+            .unwrap_or_default();
+        Self {
+            entries,
+            renamed: HashSet::new(),
+        }
+    }
+
+    /// Rereads the file, for what Nautilus or a GTK dialog changed since,
+    /// keeping the renames not written yet.
+    pub fn reload(&mut self) {
+        let mut fresh = Self::load();
+        for bookmark in fresh.entries.iter_mut() {
+            if !self.renamed.contains(&bookmark.uri) {
+                continue;
+            }
+            if let Some(renamed) = self.entries.iter().find(|old| old.uri == bookmark.uri) {
+                bookmark.label = renamed.label.clone();
+            }
+        }
+        fresh.renamed = std::mem::take(&mut self.renamed);
+        *self = fresh;
+    }
+
+    pub fn save(&mut self) -> io::Result<()> {
+        write_atomic(&Self::path(), self.serialize().as_bytes())?;
+        self.renamed.clear();
+        Ok(())
+    }
+
+    pub fn position(&self, bookmark: &Bookmark) -> Option<usize> {
+        self.entries
+            .iter()
+            .position(|entry| entry.uri == bookmark.uri)
+    }
+
+    pub fn entries(&self) -> &[Bookmark] {
+        &self.entries
+    }
+
+    pub fn contains(&self, path: &Path) -> bool {
+        self.entries
+            .iter()
+            .any(|bookmark| bookmark.path().is_some_and(|bookmarked| bookmarked == path))
+    }
+
+    pub fn add(&mut self, path: &Path) {
+        if self.contains(path) {
+            return;
+        }
+        self.entries.push(Bookmark {
+            uri: path_to_uri(path),
+            label: None,
+        });
+    }
+
+    pub fn remove(&mut self, index: usize) {
+        if index < self.entries.len() {
+            self.entries.remove(index);
+        }
+    }
+
+    /// An empty label falls back to the name of the directory.
+    pub fn rename(&mut self, index: usize, label: &str) {
+        if let Some(bookmark) = self.entries.get_mut(index) {
+            bookmark.label = Some(label.to_string()).filter(|label| !label.is_empty());
+            self.renamed.insert(bookmark.uri.clone());
+        }
+    }
+
+    pub fn move_to(&mut self, from: usize, to: usize) {
+        if from >= self.entries.len() || to >= self.entries.len() {
+            return;
+        }
+        let bookmark = self.entries.remove(from);
+        self.entries.insert(to, bookmark);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bookmarks(content: &str) -> Bookmarks {
+        Bookmarks {
+            entries: Bookmarks::parse(content),
+            renamed: HashSet::new(),
+        }
+    }
+
+    #[test]
+    fn converts_uris() {
+        let path = Path::new("/home/me/My Files/#1 100%");
+        let uri = path_to_uri(path);
+        assert_eq!(uri, "file:///home/me/My%20Files/%231%20100%25");
+        assert_eq!(uri_to_path(&uri).as_deref(), Some(path));
+
+        let path = Path::new(OsStr::from_bytes(b"/tmp/caf\xe9"));
+        assert_eq!(path_to_uri(path), "file:///tmp/caf%E9");
+        assert_eq!(uri_to_path("file:///tmp/caf%E9").as_deref(), Some(path));
+
+        assert_eq!(
+            uri_to_path("file://localhost/tmp").as_deref(),
+            Some(Path::new("/tmp"))
+        );
+        assert_eq!(uri_to_path("sftp://host/tmp"), None);
+        assert_eq!(uri_to_path("file://localhost"), None);
+    }
+
+    #[test]
+    fn parses_bookmarks() {
+        let bookmarks = bookmarks(
+            "file:///home/me/Music\n\nfile:///home/me/work%20stuff Work and more\nsftp://host/srv \n",
+        );
+        let entries = bookmarks.entries();
+        assert_eq!(entries.len(), 3);
+
+        assert_eq!(
+            entries[0].path().as_deref(),
+            Some(Path::new("/home/me/Music"))
+        );
+        assert_eq!(entries[0].custom_label(), "");
+        assert_eq!(entries[0].label(), "Music");
+
+        assert_eq!(
+            entries[1].path().as_deref(),
+            Some(Path::new("/home/me/work stuff"))
+        );
+        assert_eq!(entries[1].custom_label(), "Work and more");
+        assert_eq!(entries[1].label(), "Work and more");
+
+        assert_eq!(entries[2].path(), None);
+        assert_eq!(entries[2].label(), "sftp://host/srv");
+
+        assert!(bookmarks.contains(Path::new("/home/me/work stuff")));
+        assert!(!bookmarks.contains(Path::new("/home/me")));
+    }
+
+    #[test]
+    fn roundtrips_edits() {
+        let content = "file:///home/me/Music\nfile:///srv Server\nsftp://host/srv Remote\n";
+        let mut bookmarks = bookmarks(content);
+        assert_eq!(bookmarks.serialize(), content);
+
+        bookmarks.add(Path::new("/home/me/New Folder"));
+        bookmarks.add(Path::new("/srv"));
+        assert_eq!(
+            bookmarks.serialize(),
+            format!("{content}file:///home/me/New%20Folder\n")
+        );
+
+        bookmarks.rename(0, "Songs");
+        bookmarks.rename(1, "");
+        bookmarks.move_to(3, 0);
+        bookmarks.remove(3);
+        bookmarks.remove(7);
+        let serialized = bookmarks.serialize();
+        assert_eq!(
+            serialized,
+            "file:///home/me/New%20Folder\nfile:///home/me/Music Songs\nfile:///srv\n"
+        );
+        assert_eq!(Bookmarks::parse(&serialized), bookmarks.entries());
+    }
+}
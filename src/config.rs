@@ -0,0 +1,98 @@
+use std::path::PathBuf;
+use std::sync::LazyLock;
+
+use crate::keyfile::KeyFile;
+use crate::utils::{home_dir, xdg_config_home};
+
+pub static CONFIG: LazyLock<Config> = LazyLock::new(Config::load);
+
+/// Settings read from `$XDG_CONFIG_HOME/iced-filechooser/config.ini`, like
+///
+/// ```ini
+/// [Index]
+/// Enabled=true
+/// Roots=~;/mnt/data
+/// Excludes=node_modules;target;*.o
+///
+/// [Icons]
+/// Theme=Papirus
+///
+/// [Thumbnails]
+/// Unsandboxed=false
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct Config {
+    pub index: IndexConfig,
+    /// Used instead of the icon theme of the desktop.
+    pub icon_theme: Option<String>,
+    /// Run thumbnailers with only resource limits when bubblewrap is not
+    /// installed, instead of not at all.
+    pub unsandboxed_thumbnailers: bool,
+}
+
+#[derive(Debug, Clone)]
+pub struct IndexConfig {
+    /// Keep the index current from startup, instead of only while the
+    /// "Everywhere" search scope is used.
+    pub enabled: bool,
+    pub roots: Vec<PathBuf>,
+    /// Glob patterns matched against the names of files and directories.
+    pub excludes: Vec<String>,
+    pub index_hidden: bool,
+}
+
+impl Default for IndexConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            roots: vec![home_dir()],
+            excludes: Vec::new(),
+            index_hidden: false,
+        }
+    }
+}
+
+fn expand_home(path: &str) -> PathBuf {
+    match path.strip_prefix('~') {
+        Some(rest) => home_dir().join(rest.trim_start_matches('/')),
+        None => PathBuf::from(path),
+    }
+}
+
+impl Config {
+    pub fn path() -> PathBuf {
+        xdg_config_home().join("iced-filechooser/config.ini")
+    }
+
+    fn load() -> Self {
+        let Some(keyfile) = KeyFile::open(Self::path()) else {
+            return Self::default();
+        };
+        let default_index = IndexConfig::default();
+        let roots: Vec<PathBuf> = keyfile
+            .get_list("Index", "Roots")
+            .iter()
+            .map(|root| expand_home(root))
+            .collect();
+        Self {
+            index: IndexConfig {
+                enabled: keyfile
+                    .get_bool("Index", "Enabled")
+                    .unwrap_or(default_index.enabled),
+                roots: if roots.is_empty() {
+                    default_index.roots
+                } else {
+                    roots
+                },
+                excludes: keyfile.get_list("Index", "Excludes"),
+                index_hidden: keyfile
+                    .get_bool("Index", "IndexHidden")
+                    .unwrap_or(default_index.index_hidden),
+            },
+            icon_theme: keyfile.get("Icons", "Theme").map(|theme| theme.to_string()),
+            unsandboxed_thumbnailers: keyfile
+                .get_bool("Thumbnails", "Unsandboxed")
+                .unwrap_or(false),
+        }
+    }
+}
@@ -1,10 +1,13 @@
 use iced::widget::{
-    button, checkbox, column, container, image, row, scrollable, svg, text, text_input, Space,
+    button, checkbox, column, container, pick_list, row, scrollable, svg, text, text_input, Column,
+    Row, Space,
 };
 use iced::{alignment, Font};
-use iced::{theme, Element, Length};
+use iced::{theme, Border, Color, Element, Length, Subscription, Theme};
 use libc::{S_IRGRP, S_IROTH, S_IRUSR, S_IWGRP, S_IWOTH, S_IWUSR, S_IXGRP, S_IXOTH, S_IXUSR};
+use std::collections::{HashMap, HashSet};
 use std::str::FromStr;
+use std::time::{Duration, Instant, SystemTime};
 use std::{
     fs,
     path::{Path, PathBuf},
@@ -12,10 +15,26 @@ use std::{
 
 use iced_aw::{split, Grid, GridRow, Split};
 
-use crate::icon_cache::{get_icon_handle, IconKey};
+use crate::hex_dump::HexDump;
+use crate::highlight::HighlightedLine;
+use crate::history::History;
+use crate::icon_cache::{
+    bundled_icon, cached_icon_handle, get_icon_handle, is_cached, IconHandle, IconKey,
+};
+use crate::icon_theme::icon_theme;
+use crate::image_info::ImageInfo;
+use crate::indexer::{self, index_search};
 use crate::portal_option::{FileFilter, FilterType};
-use crate::utils::get_icon;
+use crate::query::{Query, QueryError};
+use crate::rubber_band::RubberBand;
+use crate::search::{
+    recursive_search, RecursiveSearch, SearchKind, SearchScope, MAX_SEARCH_RESULTS,
+};
+use crate::text_preview::{is_text_type, TextPreview, PREVIEW_BYTES};
+use crate::thumbnailers::has_thumbnailer;
+use crate::utils::home_dir;
 
+use chrono::{DateTime, Local};
 use mime::Mime;
 use xdg_mime::SharedMimeInfo;
 
@@ -23,9 +42,15 @@ use std::sync::LazyLock;
 
 static MIME: LazyLock<SharedMimeInfo> = LazyLock::new(SharedMimeInfo::new);
 static INPUT_ID: LazyLock<text_input::Id> = LazyLock::new(text_input::Id::unique);
+pub static LOCATION_INPUT_ID: LazyLock<text_input::Id> = LazyLock::new(text_input::Id::unique);
+pub static GRID_SCROLL_ID: LazyLock<scrollable::Id> = LazyLock::new(scrollable::Id::unique);
 
 pub const GO_PREVIOUS: &[u8] = include_bytes!("../resources/go-previous.svg");
 
+pub const GO_NEXT: &[u8] = include_bytes!("../resources/go-next.svg");
+
+pub const GO_UP: &[u8] = include_bytes!("../resources/go-up.svg");
+
 pub const SIDE_BAR_EXPAND: &[u8] = include_bytes!("../resources/sidebar-expand.svg");
 
 pub const SIDE_BAR_COLLAPSE: &[u8] = include_bytes!("../resources/sidebar-collapse.svg");
@@ -39,6 +64,23 @@ use crate::Message;
 
 const COLUMN_WIDTH: f32 = 200.0;
 
+/// The size asked from the icon theme for the grid, drawn 100 high.
+const GRID_ICON_SIZE: u32 = 96;
+
+/// Image previews are downscaled to fit this in the grid, and this in the
+/// pane beside it.
+const GRID_PREVIEW_SIZE: u32 = COLUMN_WIDTH as u32;
+const PANE_PREVIEW_SIZE: u32 = 512;
+
+/// Entries per `GridRow`.
+const GRID_COLUMNS: usize = 4;
+
+/// More entries than this are not put in the grid.
+const MAX_GRID_ENTRIES: usize = 200;
+
+/// Typing after a pause like this starts a new type-ahead prefix.
+const TYPE_AHEAD_TIMEOUT: Duration = Duration::from_millis(1000);
+
 const BUTTON_WIDTH: f32 = 170.0;
 
 #[derive(Debug)]
@@ -48,13 +90,137 @@ pub struct DirUnit {
     current_dir: PathBuf,
     glob_pattern: String,
     glob_pattern_cache: String,
+    query: Query,
+    query_error: Option<QueryError>,
+    search_scope: SearchScope,
+    search_contents: bool,
+    fuzzy: bool,
+    search: Option<RecursiveSearch>,
+    search_error: Option<String>,
+    /// Listing the recently used files instead of `current_dir`.
+    is_recent: bool,
+    /// The text of the location bar while it is typed into instead of
+    /// showing the breadcrumbs.
+    location_input: Option<String>,
+    location_error: bool,
+    /// The entry moved to with the keyboard.
+    focused: Option<PathBuf>,
+    /// The prefix typed to find an entry, and when it was last typed into.
+    type_ahead: String,
+    type_ahead_at: Option<Instant>,
+    /// The thumbnails of the images, by the path of the image, see
+    /// [`DirUnit::request_thumbnails`].
+    thumbnails: HashMap<PathBuf, PathBuf>,
+    thumbnails_requested: HashSet<PathBuf>,
+    /// The previews being loaded, see [`DirUnit::request_previews`].
+    previews_pending: HashSet<IconKey>,
+    /// The previews which could not be decoded, not tried again until the
+    /// file is modified, which changes its key.
+    previews_failed: HashSet<IconKey>,
+    image_infos: HashMap<PathBuf, ImageInfo>,
+    image_infos_requested: HashSet<PathBuf>,
+    text_previews: HashMap<PathBuf, TextPreview>,
+    text_previews_requested: HashSet<PathBuf>,
+    /// The page shown of the hex dumps paged through.
+    hex_pages: HashMap<PathBuf, usize>,
+}
+
+/// Where the arrow keys, Home and End move the keyboard focus.
+#[derive(Debug, Clone, Copy)]
+pub enum FocusMove {
+    Left,
+    Right,
+    Up,
+    Down,
+    First,
+    Last,
+}
+
+/// The focus ring around the entry moved to with the keyboard.
+struct FocusRingStyle;
+
+impl container::StyleSheet for FocusRingStyle {
+    type Style = Theme;
+
+    fn appearance(&self, style: &Self::Style) -> container::Appearance {
+        container::Appearance {
+            border: Border {
+                color: style.palette().primary,
+                width: 2.,
+                radius: 5.into(),
+            },
+            ..Default::default()
+        }
+    }
+}
+
+/// Outlines an input in red while what was typed is not valid, like a
+/// query which does not parse.
+struct InvalidInputStyle;
+
+impl text_input::StyleSheet for InvalidInputStyle {
+    type Style = Theme;
+
+    fn active(&self, style: &Self::Style) -> text_input::Appearance {
+        text_input::Appearance {
+            border: Border {
+                color: style.palette().danger,
+                width: 2.,
+                ..style.active(&theme::TextInput::Default).border
+            },
+            ..style.active(&theme::TextInput::Default)
+        }
+    }
+
+    fn focused(&self, style: &Self::Style) -> text_input::Appearance {
+        text_input::Appearance {
+            border: Border {
+                color: style.palette().danger,
+                width: 2.,
+                ..style.focused(&theme::TextInput::Default).border
+            },
+            ..style.focused(&theme::TextInput::Default)
+        }
+    }
+
+    fn placeholder_color(&self, style: &Self::Style) -> Color {
+        style.placeholder_color(&theme::TextInput::Default)
+    }
+
+    fn value_color(&self, style: &Self::Style) -> Color {
+        style.palette().danger
+    }
+
+    fn disabled_color(&self, style: &Self::Style) -> Color {
+        style.disabled_color(&theme::TextInput::Default)
+    }
+
+    fn selection_color(&self, style: &Self::Style) -> Color {
+        style.selection_color(&theme::TextInput::Default)
+    }
+
+    fn disabled(&self, style: &Self::Style) -> text_input::Appearance {
+        style.disabled(&theme::TextInput::Default)
+    }
+}
+
+/// Where to scroll to keep the entry at `index` in view. All rows are
+/// equally high, so scrolling in proportion to the row works.
+fn focus_offset(index: usize, last: usize) -> scrollable::RelativeOffset {
+    let last_row = last / GRID_COLUMNS;
+    let y = if last_row == 0 {
+        0.
+    } else {
+        (index / GRID_COLUMNS) as f32 / last_row as f32
+    };
+    scrollable::RelativeOffset { x: 0., y }
 }
 
 fn get_dir_name(dir: &Path) -> String {
     let mut output = dir
         .to_string_lossy()
         .split('/')
-        .last()
+        .next_back()
         .unwrap_or("/")
         .to_string();
     if output.is_empty() {
@@ -68,7 +234,44 @@ impl DirUnit {
         &self.current_dir
     }
 
-    fn get_parent_path(&self) -> Option<PathBuf> {
+    pub fn is_recent(&self) -> bool {
+        self.is_recent
+    }
+
+    pub fn location_input(&self) -> Option<&str> {
+        self.location_input.as_deref()
+    }
+
+    pub fn set_location_input(&mut self, input: Option<String>) {
+        self.location_input = input;
+        self.location_error = false;
+    }
+
+    /// Starts typing into the location bar, filled with the current directory.
+    pub fn edit_location(&mut self) {
+        let input = if self.is_recent {
+            String::new()
+        } else {
+            let dir = fs::canonicalize(&self.current_dir).unwrap_or(self.current_dir.clone());
+            let dir = dir.to_string_lossy();
+            if dir.ends_with('/') {
+                dir.to_string()
+            } else {
+                format!("{dir}/")
+            }
+        };
+        self.set_location_input(Some(input));
+    }
+
+    /// Marks the typed location as one which does not exist.
+    pub fn set_location_error(&mut self) {
+        self.location_error = true;
+    }
+
+    pub fn get_parent_path(&self) -> Option<PathBuf> {
+        if self.is_recent {
+            return None;
+        }
         self.current_dir.parent().map(|path| path.into())
     }
 
@@ -82,17 +285,14 @@ impl DirUnit {
         });
     }
 
-    fn get_sizebar_icon(&self, expand: bool) -> Element<Message> {
+    fn get_sizebar_icon(&self, expand: bool) -> Element<'_, Message> {
         let icon_name = if expand {
             "sidebar-expand"
         } else {
             "sidebar-collapse"
         };
-        if let Some(icon) = get_icon("Adwaita", icon_name) {
-            return svg(svg::Handle::from_path(icon))
-                .width(20)
-                .height(20)
-                .into();
+        if let Some(icon) = get_icon_handle(IconKey::themed(&icon_theme(), icon_name, 20)) {
+            return icon.view(20, 20);
         }
         let svg_source = if expand {
             SIDE_BAR_EXPAND
@@ -105,21 +305,26 @@ impl DirUnit {
             .into()
     }
 
-    fn get_prevouse_icon(&self) -> Element<Message> {
-        if let Some(icon) = get_icon("Adwaita", "go-previous") {
-            return svg(svg::Handle::from_path(icon))
-                .width(20)
-                .height(20)
-                .into();
+    fn get_navigation_icon(
+        &self,
+        icon_name: &str,
+        fallback: &'static [u8],
+    ) -> Element<'_, Message> {
+        if let Some(icon) = get_icon_handle(IconKey::themed(&icon_theme(), icon_name, 20)) {
+            return icon.view(20, 20);
         }
-        svg(svg::Handle::from_memory(GO_PREVIOUS))
+        svg(svg::Handle::from_memory(fallback))
             .width(20)
             .height(20)
             .into()
     }
 
     fn find_unit(&self, path: &Path) -> Option<&FsInfo> {
-        self.fs_infos().iter().find(|iter| 'selected: {
+        let infos = match &self.search {
+            Some(search) => search.results(),
+            None => self.fs_infos(),
+        };
+        infos.iter().find(|iter| 'selected: {
             let Ok(origin_path) = path.canonicalize() else {
                 break 'selected false;
             };
@@ -132,10 +337,281 @@ impl DirUnit {
 
     pub fn set_cache_pattern(&mut self, pattern: &str) {
         self.glob_pattern_cache = pattern.to_string();
+        self.query_error = Query::parse(pattern).err();
+        // fuzzy matching narrows the current folder while typing
+        if self.is_fuzzy() && self.search_scope == SearchScope::Folder && self.query_error.is_none()
+        {
+            self.set_pattern();
+        }
     }
 
+    /// Applies the typed pattern, a query which fails to parse is kept
+    /// highlighted in the search bar instead.
     pub fn set_pattern(&mut self) {
+        let query = match Query::parse(&self.glob_pattern_cache) {
+            Ok(query) => Some(query),
+            Err(e) if !self.search_contents => {
+                self.query_error = Some(e);
+                return;
+            }
+            Err(_) => None,
+        };
         self.glob_pattern = self.glob_pattern_cache.clone();
+        self.query = query.unwrap_or_default();
+        self.restart_search();
+    }
+
+    pub fn set_search_scope(&mut self, search_scope: SearchScope) {
+        self.search_scope = search_scope;
+        if search_scope == SearchScope::Everywhere {
+            indexer::start();
+        }
+        self.restart_search();
+    }
+
+    pub fn set_search_contents(&mut self, search_contents: bool) {
+        self.search_contents = search_contents;
+        self.restart_search();
+    }
+
+    pub fn set_fuzzy(&mut self, fuzzy: bool) {
+        self.fuzzy = fuzzy;
+        self.restart_search();
+    }
+
+    /// Drops the running search and, if subfolders or file contents are
+    /// searched for a pattern, starts a new one.
+    pub fn restart_search(&mut self) {
+        self.search = None;
+        self.search_error = None;
+        if self.glob_pattern.is_empty() {
+            return;
+        }
+        if self.search_contents {
+            match SearchKind::content(&self.glob_pattern) {
+                Ok(kind) => self.search = Some(RecursiveSearch::new(kind, SearchScope::Subfolders)),
+                Err(e) => self.search_error = Some(e.to_string()),
+            }
+        } else if self.search_scope != SearchScope::Folder {
+            let kind = if self.fuzzy {
+                SearchKind::Fuzzy(self.query.clone())
+            } else {
+                SearchKind::Name(self.query.clone())
+            };
+            self.search = Some(RecursiveSearch::new(kind, self.search_scope));
+        }
+    }
+
+    pub fn cancel_search(&mut self) {
+        if let Some(search) = self.search.as_mut() {
+            let id = search.id();
+            search.set_finished(id);
+        }
+    }
+
+    pub fn append_search_results(&mut self, id: u64, results: Vec<(FsInfo, Option<String>)>) {
+        if let Some(search) = self.search.as_mut() {
+            search.append_results(id, results);
+        }
+    }
+
+    pub fn set_search_finished(&mut self, id: u64) {
+        if let Some(search) = self.search.as_mut() {
+            search.set_finished(id);
+        }
+    }
+
+    pub fn subscription(
+        &self,
+        show_hide: bool,
+        current_filter: &FileFilter,
+    ) -> Subscription<Message> {
+        match &self.search {
+            Some(search) if !search.is_finished() && search.scope() == SearchScope::Everywhere => {
+                index_search(
+                    search.id(),
+                    search.kind().clone(),
+                    show_hide,
+                    current_filter.clone(),
+                )
+            }
+            Some(search) if !search.is_finished() => recursive_search(
+                search.id(),
+                self.current_dir.clone(),
+                search.kind().clone(),
+                show_hide,
+                current_filter.clone(),
+            ),
+            _ => Subscription::none(),
+        }
+    }
+
+    fn is_fuzzy(&self) -> bool {
+        self.fuzzy && !self.search_contents
+    }
+
+    /// The entries shown in the grid, in display order.
+    ///
+    /// In fuzzy mode they are ranked by their score, best match first.
+    pub fn visible_infos(&self, show_hide: bool, current_filter: &FileFilter) -> Vec<&FsInfo> {
+        if let Some(search) = &self.search {
+            let mut infos: Vec<&FsInfo> = search
+                .results()
+                .iter()
+                .filter(|dir| dir.is_match_filefilter(current_filter))
+                .collect();
+            if self.is_fuzzy() {
+                infos.sort_by_cached_key(|dir| {
+                    std::cmp::Reverse(self.query.fuzzy_match(dir).map(|(score, _)| score))
+                });
+            }
+            return infos;
+        }
+        let infos = self.fs_infos().iter().filter(|dir| {
+            (show_hide || !dir.is_hidden()) && dir.is_match_filefilter(current_filter)
+        });
+        if !self.is_fuzzy() {
+            return infos
+                .filter(|dir| self.search_contents || self.query.matches(dir))
+                .collect();
+        }
+        let mut scored: Vec<(i64, &FsInfo)> = infos
+            .filter_map(|dir| Some((self.query.fuzzy_match(dir)?.0, dir)))
+            .collect();
+        scored.sort_by_key(|(score, _)| std::cmp::Reverse(*score));
+        scored.into_iter().map(|(_, dir)| dir).collect()
+    }
+
+    /// The entries put in the grid, see [`DirUnit::visible_infos`].
+    pub fn shown_infos(&self, show_hide: bool, current_filter: &FileFilter) -> Vec<&FsInfo> {
+        let mut infos = self.visible_infos(show_hide, current_filter);
+        infos.truncate(MAX_GRID_ENTRIES);
+        infos
+    }
+
+    pub fn focused_info(&self, show_hide: bool, current_filter: &FileFilter) -> Option<&FsInfo> {
+        let focused = self.focused.as_ref()?;
+        self.shown_infos(show_hide, current_filter)
+            .into_iter()
+            .find(|info| info.path() == *focused)
+    }
+
+    /// Moves the keyboard focus, the first move focuses the first entry.
+    ///
+    /// Returns where to scroll to keep the focused entry in view.
+    pub fn move_focus(
+        &mut self,
+        movement: FocusMove,
+        show_hide: bool,
+        current_filter: &FileFilter,
+    ) -> Option<scrollable::RelativeOffset> {
+        let infos = self.shown_infos(show_hide, current_filter);
+        let last = infos.len().checked_sub(1)?;
+        let current = self
+            .focused
+            .as_ref()
+            .and_then(|focused| infos.iter().position(|info| info.path() == *focused));
+        let index = match (current, movement) {
+            (None, FocusMove::Last) => last,
+            (None, _) | (_, FocusMove::First) => 0,
+            (Some(_), FocusMove::Last) => last,
+            (Some(index), FocusMove::Left) => index.saturating_sub(1),
+            (Some(index), FocusMove::Right) => (index + 1).min(last),
+            (Some(index), FocusMove::Up) => index.checked_sub(GRID_COLUMNS).unwrap_or(index),
+            (Some(index), FocusMove::Down) => match index + GRID_COLUMNS {
+                below if below <= last => below,
+                // the last row may be shorter
+                _ if index / GRID_COLUMNS < last / GRID_COLUMNS => last,
+                _ => index,
+            },
+        };
+        self.focused = Some(infos[index].path());
+        Some(focus_offset(index, last))
+    }
+
+    /// Focuses the first entry whose name starts with what was typed, like
+    /// the type-ahead find of GTK. Unlike the search bar it hides nothing.
+    pub fn type_ahead(
+        &mut self,
+        typed: &str,
+        show_hide: bool,
+        current_filter: &FileFilter,
+    ) -> Option<scrollable::RelativeOffset> {
+        if self
+            .type_ahead_at
+            .is_none_or(|at| at.elapsed() > TYPE_AHEAD_TIMEOUT)
+        {
+            self.type_ahead.clear();
+        }
+        self.type_ahead.push_str(&typed.to_lowercase());
+        self.type_ahead_at = Some(Instant::now());
+        let infos = self.shown_infos(show_hide, current_filter);
+        let last = infos.len().checked_sub(1)?;
+        let index = infos
+            .iter()
+            .position(|info| info.name().to_lowercase().starts_with(&self.type_ahead))?;
+        self.focused = Some(infos[index].path());
+        Some(focus_offset(index, last))
+    }
+
+    /// The char indices of the label matched by the fuzzy query.
+    fn label_highlight(&self, info: &FsInfo, label: &str) -> Vec<usize> {
+        if !self.is_fuzzy() {
+            return Vec::new();
+        }
+        let Some((_, indices)) = self.query.fuzzy_match(info) else {
+            return Vec::new();
+        };
+        // search results are labeled with a relative path ending in the name
+        let Some(prefix) = label.strip_suffix(info.name()) else {
+            return Vec::new();
+        };
+        let offset = prefix.chars().count();
+        indices.into_iter().map(|index| index + offset).collect()
+    }
+
+    /// Search results are labeled with their path relative to the current
+    /// directory, or with their full path if they are outside of it.
+    fn entry_label(&self, info: &FsInfo) -> String {
+        if self.search.is_none() {
+            return info.name().to_string();
+        }
+        let path = info.path();
+        if let Ok(relative) = path.strip_prefix(&self.current_dir) {
+            return relative.to_string_lossy().to_string();
+        }
+        match path.strip_prefix(home_dir()) {
+            Ok(relative) => format!("~/{}", relative.to_string_lossy()),
+            Err(_) => path.to_string_lossy().to_string(),
+        }
+    }
+
+    fn search_status(&self) -> Option<String> {
+        if let Some(error) = &self.search_error {
+            return Some(error.clone());
+        }
+        if let Some(error) = self.query_error.as_ref().filter(|_| !self.search_contents) {
+            return Some(error.to_string());
+        }
+        let search = self.search.as_ref()?;
+        if !search.is_finished() {
+            let searching = match (search.kind(), search.scope()) {
+                (SearchKind::Content(_), _) => "Searching file contents",
+                (_, SearchScope::Everywhere) if indexer::is_crawling() => "Indexing",
+                (_, SearchScope::Everywhere) => "Searching everywhere",
+                _ => "Searching subfolders",
+            };
+            return Some(format!("{searching}, {} found", search.results().len()));
+        }
+        if search.scope() == SearchScope::Everywhere && indexer::is_crawling() {
+            return Some("Still indexing, some files may be missing".to_string());
+        }
+        if search.is_truncated() {
+            return Some(format!(
+                "Only the first {MAX_SEARCH_RESULTS} matches are shown"
+            ));
+        }
+        None
     }
 
     #[allow(clippy::too_many_arguments)]
@@ -146,61 +622,65 @@ impl DirUnit {
         right_splitter: Option<&u16>,
         current_selected: Option<&PathBuf>,
         select_dir: bool,
+        multi_select: bool,
         seclected_paths: &[PathBuf],
         current_filter: &FileFilter,
-    ) -> Element<Message> {
+        theme: &Theme,
+    ) -> Element<'_, Message> {
         let mut grid = Grid::new().column_width(COLUMN_WIDTH);
-        let filter_way = |dir: &&FsInfo| {
-            (show_hide || !dir.is_hidden())
-                && glob::Pattern::new(&format!("*{}*", self.glob_pattern))
-                    .is_ok_and(|pattern| pattern.matches(dir.name()))
-                && dir.is_match_filefilter(current_filter)
-        };
-        let nottoshowall = self.fs_infos().iter().filter(filter_way).count() > 200;
-        if nottoshowall {
-            let mut iter = self.fs_infos().iter().filter(filter_way);
-            let mut views = vec![];
-            for index in 0..200 {
-                let dir = iter.next().unwrap();
-                views.push(dir.view(
-                    select_dir,
-                    preview_image,
-                    current_selected,
-                    seclected_paths.contains(&dir.path()),
-                ));
-                if (index + 1) % 4 == 0 {
-                    let mut newviews = vec![];
-                    std::mem::swap(&mut views, &mut newviews);
-                    grid = grid.push(GridRow::with_elements(newviews));
-                }
-            }
-            if !views.is_empty() {
-                grid = grid.push(GridRow::with_elements(views));
-            }
-        } else {
-            let mut views = vec![];
-            for (index, dir) in self.fs_infos().iter().filter(filter_way).enumerate() {
-                views.push(dir.view(
-                    select_dir,
-                    preview_image,
-                    current_selected,
-                    seclected_paths.contains(&dir.path()),
-                ));
-                if (index + 1) % 4 == 0 {
-                    let mut newviews = vec![];
-                    std::mem::swap(&mut views, &mut newviews);
-                    grid = grid.push(GridRow::with_elements(newviews));
-                }
-            }
-            if !views.is_empty() {
-                grid = grid.push(GridRow::with_elements(views));
+        let infos = self.visible_infos(show_hide, current_filter);
+        let nottoshowall = infos.len() > MAX_GRID_ENTRIES;
+        let mut views = vec![];
+        let mut paths = vec![];
+        for (index, dir) in infos.into_iter().take(MAX_GRID_ENTRIES).enumerate() {
+            paths.push(dir.path());
+            let label = self.entry_label(dir);
+            let highlight = self.label_highlight(dir, &label);
+            let thumbnail = preview_image
+                .then(|| self.thumbnails.get(&dir.path()))
+                .flatten();
+            views.push(dir.view(
+                label,
+                highlight,
+                select_dir,
+                thumbnail,
+                current_selected,
+                seclected_paths.contains(&dir.path()),
+                self.focused.as_ref() == Some(&dir.path()),
+                theme,
+            ));
+            if (index + 1) % GRID_COLUMNS == 0 {
+                let mut newviews = vec![];
+                std::mem::swap(&mut views, &mut newviews);
+                grid = grid.push(GridRow::with_elements(newviews));
             }
-        };
+        }
+        if !views.is_empty() {
+            grid = grid.push(GridRow::with_elements(views));
+        }
         let rightviewinfo = current_selected.as_ref().and_then(|p| self.find_unit(p));
 
-        let mainview: Element<Message> = if nottoshowall {
-            column![
-                container(grid).width(Length::Fill).center_x(),
+        let mut mainview = column![].width(Length::Fill);
+        if let Some(status) = self.search_status() {
+            mainview = mainview.push(
+                text(status)
+                    .height(20)
+                    .width(Length::Fill)
+                    .horizontal_alignment(alignment::Horizontal::Center),
+            );
+        }
+        if multi_select {
+            mainview = mainview.push(RubberBand::new(
+                grid,
+                paths,
+                Message::RequestRubberBandSelect,
+                Message::RubberBandFinished,
+            ));
+        } else {
+            mainview = mainview.push(container(grid).width(Length::Fill).center_x());
+        }
+        if nottoshowall {
+            mainview = mainview.push(
                 text("To much, not to show")
                     .font(Font {
                         weight: iced::font::Weight::Medium,
@@ -208,18 +688,23 @@ impl DirUnit {
                     })
                     .height(20)
                     .width(Length::Fill)
-                    .horizontal_alignment(alignment::Horizontal::Center)
-            ]
-            .width(Length::Fill)
-            .into()
-        } else {
-            container(grid).center_x().width(Length::Fill).into()
-        };
+                    .horizontal_alignment(alignment::Horizontal::Center),
+            );
+        }
 
         match rightviewinfo {
             Some(info) => Split::new(
-                scrollable(mainview),
-                info.right_view(),
+                scrollable(mainview)
+                    .id(GRID_SCROLL_ID.clone())
+                    .on_scroll(|viewport| Message::RequestGridScrolled(viewport.absolute_offset())),
+                info.right_view(
+                    self.search
+                        .as_ref()
+                        .and_then(|search| search.snippet(&info.path())),
+                    self.image_infos.get(&info.path()),
+                    self.text_previews.get(&info.path()),
+                    self.hex_pages.get(&info.path()).copied().unwrap_or(0),
+                ),
                 right_splitter.copied(),
                 split::Axis::Vertical,
                 Message::RequestAdjustRightSplitter,
@@ -228,11 +713,15 @@ impl DirUnit {
             .height(Length::Fill)
             .padding(10.0)
             .into(),
-            None => scrollable(mainview).height(Length::Fill).into(),
+            None => scrollable(mainview)
+                .id(GRID_SCROLL_ID.clone())
+                .on_scroll(|viewport| Message::RequestGridScrolled(viewport.absolute_offset()))
+                .height(Length::Fill)
+                .into(),
         }
     }
 
-    fn loading_page(&self) -> Element<Message> {
+    fn loading_page(&self) -> Element<'_, Message> {
         container(column![
             Space::new(Length::Fill, Length::Fill),
             row![
@@ -261,9 +750,11 @@ impl DirUnit {
         right_splitter: Option<&u16>,
         current_selected: Option<&PathBuf>,
         select_dir: bool,
+        multi_select: bool,
         seclected_paths: &[PathBuf],
         current_filter: &FileFilter,
-    ) -> Element<Message> {
+        theme: &Theme,
+    ) -> Element<'_, Message> {
         if self.is_end {
             self.main_grid(
                 show_hide,
@@ -271,8 +762,10 @@ impl DirUnit {
                 right_splitter,
                 current_selected,
                 select_dir,
+                multi_select,
                 seclected_paths,
                 current_filter,
+                theme,
             )
         } else {
             self.loading_page()
@@ -280,27 +773,39 @@ impl DirUnit {
     }
 
     #[allow(clippy::too_many_arguments)]
-    pub fn view(
-        &self,
+    pub fn view<'a>(
+        &'a self,
+        sidebar: Option<Element<'a, Message>>,
         show_hide: bool,
         preview_image: bool,
         right_splitter: Option<&u16>,
         current_selected: Option<&PathBuf>,
         select_dir: bool,
+        multi_select: bool,
         seclected_paths: &[PathBuf],
         current_filter: &FileFilter,
-    ) -> Element<Message> {
+        history: &History,
+        theme: &Theme,
+    ) -> Element<'a, Message> {
+        let bottom_view = self.bottom_view(
+            show_hide,
+            preview_image,
+            right_splitter,
+            current_selected,
+            select_dir,
+            multi_select,
+            seclected_paths,
+            current_filter,
+            theme,
+        );
+        let show_sidebar = sidebar.is_some();
+        let main_view: Element<Message> = match sidebar {
+            Some(sidebar) => row![sidebar, bottom_view].spacing(5).into(),
+            None => bottom_view,
+        };
         column![
-            self.title_bar(show_hide, preview_image),
-            self.bottom_view(
-                show_hide,
-                preview_image,
-                right_splitter,
-                current_selected,
-                select_dir,
-                seclected_paths,
-                current_filter
-            ),
+            self.title_bar(show_sidebar, show_hide, preview_image, history),
+            main_view,
             self.confirm_buttons(),
             Space::new(0, 5.)
         ]
@@ -308,7 +813,7 @@ impl DirUnit {
         .into()
     }
 
-    fn confirm_buttons(&self) -> Element<Message> {
+    fn confirm_buttons(&self) -> Element<'_, Message> {
         row![
             Space::new(Length::Fill, 5.),
             button(text("Confirm")).on_press(Message::Confirm),
@@ -320,26 +825,55 @@ impl DirUnit {
         .into()
     }
 
-    fn searchbar(&self) -> Element<Message> {
-        text_input("Search Pattern", self.glob_pattern_cache.as_str())
+    fn searchbar(&self) -> Element<'_, Message> {
+        let placeholder = if self.search_contents {
+            "Search Text or /Regex/"
+        } else {
+            "Search, like type:image size:>2M"
+        };
+        let mut input = text_input(placeholder, self.glob_pattern_cache.as_str())
             .id(INPUT_ID.clone())
             .on_input(Message::SearchPatternCachedChanged)
             .on_submit(Message::SearchPatternChanged)
             .padding(5)
-            .size(15)
-            .into()
+            .size(15);
+        if self.query_error.is_some() && !self.search_contents {
+            input = input.style(theme::TextInput::Custom(Box::new(InvalidInputStyle)));
+        }
+        input.into()
     }
 
-    fn title_bar(&self, show_hide: bool, preview_image: bool) -> Element<Message> {
-        let current_dir = fs::canonicalize(&self.current_dir).unwrap();
-
+    fn title_bar(
+        &self,
+        show_sidebar: bool,
+        show_hide: bool,
+        preview_image: bool,
+        history: &History,
+    ) -> Element<'_, Message> {
         let mut rowvec: Vec<Element<Message>> = Vec::new();
-        let btn_sizebar = button(self.get_sizebar_icon(true))
+        let btn_sizebar = button(self.get_sizebar_icon(!show_sidebar))
             .style(theme::Button::Secondary)
+            .on_press(Message::RequestShowSidebar(!show_sidebar))
             .into();
         rowvec.push(btn_sizebar);
+        rowvec.push(
+            button(self.get_navigation_icon("go-previous", GO_PREVIOUS))
+                .style(theme::Button::Secondary)
+                .on_press_maybe(history.can_go_back().then_some(Message::RequestGoBack))
+                .into(),
+        );
+        rowvec.push(
+            button(self.get_navigation_icon("go-next", GO_NEXT))
+                .style(theme::Button::Secondary)
+                .on_press_maybe(
+                    history
+                        .can_go_forward()
+                        .then_some(Message::RequestGoForward),
+                )
+                .into(),
+        );
         if let Some(parent) = self.get_parent_path() {
-            let btn: Element<Message> = button(self.get_prevouse_icon())
+            let btn: Element<Message> = button(self.get_navigation_icon("go-up", GO_UP))
                 .style(theme::Button::Secondary)
                 .on_press(Message::RequestEnter(parent))
                 .into();
@@ -348,23 +882,44 @@ impl DirUnit {
 
         let mut dirbtn: Vec<Element<Message>> = Vec::new();
 
-        let mut current_path_dir = current_dir.clone();
-
-        dirbtn.push(
-            button(text(get_dir_name(&current_path_dir)).shaping(text::Shaping::Advanced))
-                .on_press(Message::RequestEnter(current_path_dir.clone()))
+        rowvec.push(
+            button(text("✎").shaping(text::Shaping::Advanced))
+                .style(theme::Button::Secondary)
+                .on_press(Message::RequestEditLocation(self.location_input.is_none()))
                 .into(),
         );
-
-        while let Some(parent) = current_path_dir.parent() {
-            current_path_dir = PathBuf::from(parent);
-            let mut newbtns = vec![button(
-                text(get_dir_name(&current_path_dir)).shaping(text::Shaping::Advanced),
-            )
-            .on_press(Message::RequestEnter(current_path_dir.clone()))
-            .into()];
-            newbtns.append(&mut dirbtn);
-            dirbtn = newbtns;
+        if let Some(location) = &self.location_input {
+            let mut input = text_input("Type a path, ~ or file://", location)
+                .id(LOCATION_INPUT_ID.clone())
+                .on_input(Message::LocationInputChanged)
+                .on_submit(Message::LocationSubmitted)
+                .on_paste(Message::LocationPasted)
+                .padding(5)
+                .size(15);
+            if self.location_error {
+                input = input.style(theme::TextInput::Custom(Box::new(InvalidInputStyle)));
+            }
+            dirbtn.push(input.into());
+        } else if self.is_recent {
+            dirbtn.push(button(text("Recent")).into());
+        } else {
+            let mut current_path_dir =
+                fs::canonicalize(&self.current_dir).unwrap_or(self.current_dir.clone());
+            dirbtn.push(
+                button(text(get_dir_name(&current_path_dir)).shaping(text::Shaping::Advanced))
+                    .on_press(Message::RequestEnter(current_path_dir.clone()))
+                    .into(),
+            );
+            while let Some(parent) = current_path_dir.parent() {
+                current_path_dir = PathBuf::from(parent);
+                let mut newbtns = vec![button(
+                    text(get_dir_name(&current_path_dir)).shaping(text::Shaping::Advanced),
+                )
+                .on_press(Message::RequestEnter(current_path_dir.clone()))
+                .into()];
+                newbtns.append(&mut dirbtn);
+                dirbtn = newbtns;
+            }
         }
 
         rowvec.append(&mut dirbtn);
@@ -378,6 +933,40 @@ impl DirUnit {
                 .size(20)
                 .into(),
         ]);
+        rowvec.push(
+            pick_list(
+                &SearchScope::ALL[..],
+                Some(self.search_scope),
+                Message::RequestSearchScope,
+            )
+            .padding(5)
+            .text_size(15)
+            .into(),
+        );
+        rowvec.push(
+            checkbox("search contents", self.search_contents)
+                .on_toggle(Message::RequestSearchContents)
+                .size(20)
+                .into(),
+        );
+        rowvec.push(
+            checkbox("fuzzy", self.fuzzy)
+                .on_toggle(Message::RequestFuzzySearch)
+                .size(20)
+                .into(),
+        );
+        if self
+            .search
+            .as_ref()
+            .is_some_and(|search| !search.is_finished())
+        {
+            rowvec.push(
+                button(text("Stop"))
+                    .style(theme::Button::Secondary)
+                    .on_press(Message::RequestCancelSearch)
+                    .into(),
+            );
+        }
         rowvec.push(self.searchbar());
         container(
             row(rowvec)
@@ -396,6 +985,162 @@ impl DirUnit {
             current_dir: dir.to_path_buf(),
             glob_pattern: String::new(),
             glob_pattern_cache: String::new(),
+            query: Query::default(),
+            query_error: None,
+            search_scope: SearchScope::default(),
+            search_contents: false,
+            fuzzy: false,
+            search: None,
+            search_error: None,
+            is_recent: false,
+            location_input: None,
+            location_error: false,
+            focused: None,
+            type_ahead: String::new(),
+            type_ahead_at: None,
+            thumbnails: HashMap::new(),
+            thumbnails_requested: HashSet::new(),
+            previews_pending: HashSet::new(),
+            previews_failed: HashSet::new(),
+            image_infos: HashMap::new(),
+            image_infos_requested: HashSet::new(),
+            text_previews: HashMap::new(),
+            text_previews_requested: HashSet::new(),
+            hex_pages: HashMap::new(),
+        }
+    }
+
+    /// The virtual "Recent" location, filled by [`DirUnit::set_recent_infos`].
+    pub fn recent() -> Self {
+        Self {
+            is_recent: true,
+            ..Self::enter(&home_dir())
+        }
+    }
+
+    /// Keeps the order of the recent files, most recent first.
+    pub fn set_recent_infos(&mut self, infos: Vec<FsInfo>) {
+        self.infos = infos;
+        self.is_end = true;
+    }
+
+    /// The images shown in the grid which have not had their thumbnail
+    /// asked for yet, which are marked as asked for.
+    pub fn request_thumbnails(
+        &mut self,
+        show_hide: bool,
+        current_filter: &FileFilter,
+    ) -> Vec<(PathBuf, Vec<Mime>)> {
+        let requests: Vec<(PathBuf, Vec<Mime>)> = self
+            .shown_infos(show_hide, current_filter)
+            .into_iter()
+            // svg files are drawn from the original at any size
+            .filter(|info| {
+                (info.is_image() && !info.is_svg()) || has_thumbnailer(info.mime_types())
+            })
+            .filter(|info| !self.thumbnails_requested.contains(&info.path()))
+            .map(|info| (info.path(), info.mime_types().to_vec()))
+            .collect();
+        self.thumbnails_requested
+            .extend(requests.iter().map(|(path, _)| path.clone()));
+        requests
+    }
+
+    pub fn set_thumbnail(&mut self, path: PathBuf, thumbnail: Option<PathBuf>) {
+        if !self.thumbnails_requested.contains(&path) {
+            return;
+        }
+        if let Some(thumbnail) = thumbnail {
+            self.thumbnails.insert(path, thumbnail);
+        }
+    }
+
+    /// The previews shown in the grid and for `selected` beside it which are
+    /// neither in the icon cache nor being loaded, which are marked as being
+    /// loaded.
+    pub fn request_previews(
+        &mut self,
+        preview_image: bool,
+        selected: Option<&PathBuf>,
+        show_hide: bool,
+        current_filter: &FileFilter,
+    ) -> Vec<IconKey> {
+        let shown = self.shown_infos(show_hide, current_filter);
+        let grid = shown.into_iter().filter_map(|info| {
+            let thumbnail = preview_image
+                .then(|| self.thumbnails.get(&info.path()))
+                .flatten();
+            info.grid_preview_key(thumbnail)
+        });
+        let pane = selected
+            .and_then(|path| self.find_unit(path))
+            .and_then(|info| info.pane_preview_key());
+        let keys: Vec<IconKey> = grid
+            .chain(pane)
+            .filter(|key| {
+                !self.previews_pending.contains(key)
+                    && !self.previews_failed.contains(key)
+                    && !is_cached(key)
+            })
+            .collect();
+        self.previews_pending.extend(keys.iter().cloned());
+        keys
+    }
+
+    /// The image selected, if its headers haven't been asked for yet.
+    pub fn request_image_info(&mut self, selected: Option<&PathBuf>) -> Option<PathBuf> {
+        let info = self.find_unit(selected?)?;
+        if !info.is_image() || info.is_svg() {
+            return None;
+        }
+        let path = info.path();
+        self.image_infos_requested
+            .insert(path.clone())
+            .then_some(path)
+    }
+
+    pub fn set_image_info(&mut self, path: PathBuf, image_info: Option<ImageInfo>) {
+        if let Some(image_info) = image_info {
+            self.image_infos.insert(path, image_info);
+        }
+    }
+
+    /// The file selected, if it hasn't been read yet and isn't an image,
+    /// which is previewed as such. FIFOs and devices are not read, they may
+    /// block or never end.
+    pub fn request_text_preview(
+        &mut self,
+        selected: Option<&PathBuf>,
+    ) -> Option<(PathBuf, Vec<Mime>)> {
+        let info = self.find_unit(selected?)?;
+        if !info.is_file() || (info.is_image() && !is_text_type(info.mime_types())) {
+            return None;
+        }
+        if !fs::metadata(info.path()).is_ok_and(|metadata| metadata.file_type().is_file()) {
+            return None;
+        }
+        let (path, mime_types) = (info.path(), info.mime_types().to_vec());
+        self.text_previews_requested
+            .insert(path.clone())
+            .then_some((path, mime_types))
+    }
+
+    pub fn set_text_preview(&mut self, path: PathBuf, preview: Option<TextPreview>) {
+        if let Some(preview) = preview {
+            self.text_previews.insert(path, preview);
+        }
+    }
+
+    pub fn set_hex_page(&mut self, path: PathBuf, page: usize) {
+        self.hex_pages.insert(path, page);
+    }
+
+    /// A preview which was cancelled, or evicted from the cache since, is
+    /// loaded again when next requested, one which failed is not.
+    pub fn set_preview_loaded(&mut self, key: IconKey, failed: bool) {
+        self.previews_pending.remove(&key);
+        if failed {
+            self.previews_failed.insert(key);
         }
     }
 
@@ -423,65 +1168,60 @@ pub async fn update_dir_infos<P: AsRef<Path>>(path: P) -> (Vec<FsInfo>, PathBuf)
 
         tokio::time::sleep(std::time::Duration::from_nanos(5)).await;
 
-        let path = file.path();
-        use std::os::unix::fs::MetadataExt;
-        let permission = parse_permissions(metadata.mode());
-        let mime = &MIME;
-        if metadata.is_symlink() {
-            let Ok(realpath) = tokio::fs::read_link(&path).await else {
-                continue;
-            };
-            if path.is_dir() {
-                fs_infos.push(FsInfo::Dir {
-                    path,
-                    name,
-                    permission,
-                    symlink: Some(realpath),
-                });
-            } else {
-                let mimeinfo = mime.get_mime_types_from_file_name(&name);
-                let icon = mimeinfo
-                    .first()
-                    .and_then(|info| mime.lookup_generic_icon_name(info))
-                    .unwrap_or(TEXT_ICON.to_string());
-                fs_infos.push(FsInfo::File {
-                    path,
-                    icon,
-                    permission,
-                    name,
-                    symlink: Some(realpath),
-                    mimeinfo,
-                });
-            }
-            continue;
-        }
-        if metadata.is_dir() {
-            fs_infos.push(FsInfo::Dir {
-                path,
-                name,
-                permission,
-                symlink: None,
-            });
-        } else {
-            let mimeinfo = mime.get_mime_types_from_file_name(&name);
-            let icon = mimeinfo
-                .first()
-                .and_then(|info| mime.lookup_generic_icon_name(info))
-                .unwrap_or(TEXT_ICON.to_string());
-            fs_infos.push(FsInfo::File {
-                path,
-                icon,
-                permission,
-                name,
-                symlink: None,
-                mimeinfo,
-            })
+        if let Some(info) = read_fs_info(file.path(), name, &metadata) {
+            fs_infos.push(info);
         }
     }
 
     (fs_infos, path.as_ref().into())
 }
 
+/// Builds the [`FsInfo`] of one directory entry, `metadata` must not follow symlinks.
+pub fn read_fs_info(path: PathBuf, name: String, metadata: &fs::Metadata) -> Option<FsInfo> {
+    use std::os::unix::fs::MetadataExt;
+    let permission = parse_permissions(metadata.mode());
+    let symlink = if metadata.is_symlink() {
+        Some(fs::read_link(&path).ok()?)
+    } else {
+        None
+    };
+    // size and times of a symlink are the ones of its target
+    let target = if symlink.is_some() {
+        fs::metadata(&path).ok()
+    } else {
+        None
+    };
+    let metadata = target.as_ref().unwrap_or(metadata);
+    let size = metadata.len();
+    let modified = metadata.modified().ok();
+    if metadata.is_dir() {
+        return Some(FsInfo::Dir {
+            path,
+            name,
+            permission,
+            symlink,
+            size,
+            modified,
+        });
+    }
+    let mime = &MIME;
+    let mimeinfo = mime.get_mime_types_from_file_name(&name);
+    let icon = mimeinfo
+        .first()
+        .and_then(|info| mime.lookup_generic_icon_name(info))
+        .unwrap_or(TEXT_ICON.to_string());
+    Some(FsInfo::File {
+        path,
+        icon,
+        permission,
+        name,
+        symlink,
+        mimeinfo,
+        size,
+        modified,
+    })
+}
+
 #[derive(Debug, Clone)]
 pub enum FsInfo {
     File {
@@ -491,12 +1231,16 @@ pub enum FsInfo {
         name: String,
         symlink: Option<PathBuf>,
         mimeinfo: Vec<Mime>,
+        size: u64,
+        modified: Option<SystemTime>,
     },
     Dir {
         path: PathBuf,
         name: String,
         permission: String,
         symlink: Option<PathBuf>,
+        size: u64,
+        modified: Option<SystemTime>,
     },
 }
 fn triplet(mode: u32, read: u32, write: u32, execute: u32) -> String {
@@ -514,7 +1258,7 @@ fn triplet(mode: u32, read: u32, write: u32, execute: u32) -> String {
 }
 
 impl FsInfo {
-    fn is_match_filefilter(&self, filefilter: &FileFilter) -> bool {
+    pub fn is_match_filefilter(&self, filefilter: &FileFilter) -> bool {
         if self.is_dir() {
             return true;
         }
@@ -549,6 +1293,52 @@ impl FsInfo {
     }
 }
 
+pub fn format_size(size: u64) -> String {
+    const UNITS: [&str; 5] = ["B", "KiB", "MiB", "GiB", "TiB"];
+    let mut size = size as f64;
+    let mut unit = 0;
+    while size >= 1024. && unit < UNITS.len() - 1 {
+        size /= 1024.;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{size} {}", UNITS[unit])
+    } else {
+        format!("{size:.1} {}", UNITS[unit])
+    }
+}
+
+/// Renders `label` with the chars at the `highlight` indices emphasized in
+/// the primary color of `theme`.
+fn highlighted_label<'a>(label: &str, highlight: &[usize], theme: &Theme) -> Element<'a, Message> {
+    let segment = |content: String, emphasized: bool| {
+        let segment = text(content).shaping(text::Shaping::Advanced);
+        if emphasized {
+            segment.style(theme.palette().primary).font(Font {
+                weight: iced::font::Weight::Bold,
+                ..Default::default()
+            })
+        } else {
+            segment
+        }
+    };
+    let mut segments = row![];
+    let mut current = String::new();
+    let mut emphasized = false;
+    for (index, c) in label.chars().enumerate() {
+        let is_match = highlight.contains(&index);
+        if is_match != emphasized && !current.is_empty() {
+            segments = segments.push(segment(std::mem::take(&mut current), emphasized));
+        }
+        emphasized = is_match;
+        current.push(c);
+    }
+    if !current.is_empty() {
+        segments = segments.push(segment(current, emphasized));
+    }
+    segments.into()
+}
+
 fn parse_permissions(mode: u32) -> String {
     let user = triplet(mode, S_IRUSR, S_IWUSR, S_IXUSR);
     let group = triplet(mode, S_IRGRP, S_IWGRP, S_IXGRP);
@@ -579,6 +1369,33 @@ impl FsInfo {
         self.is_dir() && self.path().read_dir().is_ok()
     }
 
+    /// Matches a glob against the name, or against the MIME types if it
+    /// has a `/`, like `image/*`.
+    pub fn matches_pattern(&self, pattern: &glob::Pattern) -> bool {
+        if !pattern.as_str().contains('/') {
+            return pattern.matches(self.name());
+        }
+        match self {
+            FsInfo::Dir { .. } => pattern.matches("inode/directory"),
+            FsInfo::File { mimeinfo, .. } => {
+                mimeinfo.iter().any(|mime| pattern.matches(mime.as_ref()))
+            }
+        }
+    }
+
+    /// Directories can be picked only by a directory chooser, files only by
+    /// a file chooser.
+    pub fn is_selectable(&self, select_dir: bool) -> bool {
+        self.is_readable() && (self.is_dir() == select_dir)
+    }
+
+    pub fn mime_types(&self) -> &[Mime] {
+        match self {
+            FsInfo::Dir { .. } => &[],
+            FsInfo::File { mimeinfo, .. } => mimeinfo,
+        }
+    }
+
     pub fn is_svg(&self) -> bool {
         let FsInfo::File {
             path,
@@ -587,6 +1404,8 @@ impl FsInfo {
             name,
             symlink,
             mimeinfo,
+            size,
+            modified,
         } = self
         else {
             return false;
@@ -598,7 +1417,7 @@ impl FsInfo {
         self.icon() == "image-x-generic"
     }
 
-    fn get_text_icon(&self, theme: &str) -> Option<String> {
+    fn get_text_icon(&self, theme: &str) -> Option<IconHandle> {
         let icon = self.icon();
         if icon != "text-x-generic" {
             return None;
@@ -611,7 +1430,7 @@ impl FsInfo {
         let iconname = mimeinfo.first()?;
 
         let newicon = iconname.to_string().replace('/', "-");
-        get_icon(theme, newicon.as_str())
+        get_icon_handle(IconKey::themed(theme, &newicon, GRID_ICON_SIZE))
     }
 
     pub fn icon(&self) -> &str {
@@ -628,6 +1447,20 @@ impl FsInfo {
         }
     }
 
+    pub fn size(&self) -> u64 {
+        match self {
+            FsInfo::Dir { size, .. } => *size,
+            FsInfo::File { size, .. } => *size,
+        }
+    }
+
+    pub fn modified(&self) -> Option<SystemTime> {
+        match self {
+            FsInfo::Dir { modified, .. } => *modified,
+            FsInfo::File { modified, .. } => *modified,
+        }
+    }
+
     pub fn is_hidden(&self) -> bool {
         self.name().starts_with('.')
     }
@@ -646,62 +1479,278 @@ impl FsInfo {
         }
     }
 
-    fn get_default_generate_icon_handle(&self, theme: &str) -> svg::Handle {
-        if let Some(icon) = get_icon(theme, self.icon()) {
-            return get_icon_handle(IconKey::Path(icon));
+    fn get_default_generate_icon_handle(&self, theme: &str) -> IconHandle {
+        get_icon_handle(IconKey::themed(theme, self.icon(), GRID_ICON_SIZE))
+            .unwrap_or_else(|| bundled_icon(self.is_dir()))
+    }
+
+    fn get_default_icon_handle(&self) -> IconHandle {
+        let theme = icon_theme();
+        self.get_text_icon(&theme)
+            .unwrap_or_else(|| self.get_default_generate_icon_handle(&theme))
+    }
+
+    fn preview_key(&self, path: PathBuf, size: u32) -> IconKey {
+        IconKey::Preview {
+            path,
+            modified: self.modified(),
+            size,
+        }
+    }
+
+    /// Svg files are shown as themselves in the grid, other images by their
+    /// thumbnail, never by decoding the original.
+    fn grid_preview_key(&self, thumbnail: Option<&PathBuf>) -> Option<IconKey> {
+        if self.is_svg() {
+            return Some(self.preview_key(self.path(), GRID_PREVIEW_SIZE));
         }
+        thumbnail.map(|thumbnail| self.preview_key(thumbnail.clone(), GRID_PREVIEW_SIZE))
+    }
+
+    fn pane_preview_key(&self) -> Option<IconKey> {
+        (self.is_svg() || self.is_image()).then(|| self.preview_key(self.path(), PANE_PREVIEW_SIZE))
+    }
+
+    /// Previews are drawn once a worker has loaded them into the icon cache,
+    /// the icon of the type stands in for them until then.
+    fn grid_icon(&self, thumbnail: Option<&PathBuf>) -> Element<'_, Message> {
+        let preview = self
+            .grid_preview_key(thumbnail)
+            .and_then(|key| cached_icon_handle(&key));
+        match preview {
+            Some(preview) => preview.view(Length::Fill, 100),
+            None => self.get_default_icon_handle().view(Length::Fill, 100),
+        }
+    }
+
+    fn pane_icon(&self) -> Element<'_, Message> {
+        let preview = self
+            .pane_preview_key()
+            .and_then(|key| cached_icon_handle(&key));
+        match preview {
+            Some(preview) if self.is_svg() => preview.view(Length::Fill, 100),
+            Some(preview) => preview.view(Length::Fill, Length::Shrink),
+            None => self.get_default_icon_handle().view(Length::Fill, 100),
+        }
+    }
+
+    fn metadata_line(&self) -> String {
+        let modified = self
+            .modified()
+            .map(|modified| {
+                DateTime::<Local>::from(modified)
+                    .format("%Y-%m-%d %H:%M")
+                    .to_string()
+            })
+            .unwrap_or_default();
         if self.is_dir() {
-            get_icon_handle(IconKey::Dir)
-        } else {
-            get_icon_handle(IconKey::Text)
+            return modified;
         }
+        format!("{}  {modified}", format_size(self.size()))
     }
 
-    fn get_default_icon_handle(&self) -> svg::Handle {
-        if let Some(icon) = self.get_text_icon("Adwaita") {
-            return get_icon_handle(IconKey::Path(icon));
+    fn image_info_lines(image_info: &ImageInfo) -> Vec<String> {
+        let mut lines = vec![format!(
+            "{} × {}, {}",
+            image_info.width, image_info.height, image_info.color
+        )];
+        lines.extend(image_info.camera.clone());
+        lines.extend(
+            image_info
+                .captured
+                .as_ref()
+                .map(|captured| format!("Taken {captured}")),
+        );
+        if image_info.has_gps {
+            lines.push("Has GPS location".to_string());
         }
-        self.get_default_generate_icon_handle("Adwaita")
+        lines
     }
 
-    fn get_icon(&self, preview_image: bool) -> Element<Message> {
-        if self.is_svg() {
-            return svg(svg::Handle::from_path(self.path()))
-                .height(100)
+    /// The highlighted lines as rows of colored spans, and the lines after
+    /// them as they are.
+    fn preview_text<'a>(
+        content: &'a str,
+        highlighted: Option<&'a [HighlightedLine]>,
+    ) -> Element<'a, Message> {
+        let plain = |content: String| {
+            text(content)
+                .font(Font::MONOSPACE)
+                .shaping(text::Shaping::Advanced)
+                .size(13)
+        };
+        let Some(highlighted) = highlighted else {
+            return plain(content.to_string()).into();
+        };
+        let mut lines = Column::with_children(highlighted.iter().map(|line| {
+            if line.is_empty() {
+                return plain(" ".to_string()).into();
+            }
+            Row::with_children(
+                line.iter()
+                    .map(|(color, span)| plain(span.clone()).style(*color).into()),
+            )
+            .into()
+        }));
+        let rest: Vec<&str> = content.lines().skip(highlighted.len()).collect();
+        if !rest.is_empty() {
+            lines = lines.push(plain(rest.join("\n")));
+        }
+        lines.into()
+    }
+
+    /// The guessed type and the size of a file, and a page of the dump of
+    /// its start with buttons to the pages before and after.
+    fn hex_dump_view(&self, dump: &HexDump, page: usize) -> Element<'_, Message> {
+        if dump.size == 0 {
+            return text("Empty file")
+                .horizontal_alignment(alignment::Horizontal::Center)
                 .width(Length::Fill)
                 .into();
         }
-        if self.is_image() && preview_image {
-            return image(self.path()).width(Length::Fill).into();
+        let pages = dump.pages();
+        let page = page.min(pages - 1);
+        let kind = dump.kind.unwrap_or("Unknown data");
+        let mut status = format!("{kind}, {}", format_size(dump.size));
+        if (dump.shown_bytes() as u64) < dump.size {
+            status.push_str(&format!(
+                ", first {}",
+                format_size(dump.shown_bytes() as u64)
+            ));
         }
-        svg(self.get_default_icon_handle())
-            .height(100)
-            .width(Length::Fill)
-            .into()
+        let path = self.path();
+        let pager = row![
+            button(text("<").size(12)).padding([2, 8]).on_press_maybe(
+                (page > 0).then(|| Message::RequestHexPage((path.clone(), page - 1)))
+            ),
+            text(format!("Page {} of {pages}", page + 1))
+                .size(12)
+                .horizontal_alignment(alignment::Horizontal::Center)
+                .width(Length::Fill),
+            button(text(">").size(12)).padding([2, 8]).on_press_maybe(
+                (page + 1 < pages).then(|| Message::RequestHexPage((path.clone(), page + 1)))
+            ),
+        ]
+        .align_items(iced::Alignment::Center);
+        column![
+            text(status)
+                .size(12)
+                .horizontal_alignment(alignment::Horizontal::Center)
+                .width(Length::Fill),
+            pager,
+            scrollable(text(dump.page(page)).font(Font::MONOSPACE).size(12)).direction(
+                scrollable::Direction::Both {
+                    vertical: scrollable::Properties::default(),
+                    horizontal: scrollable::Properties::default(),
+                }
+            ),
+        ]
+        .spacing(4)
+        .into()
     }
 
-    fn right_view(&self) -> Element<Message> {
+    fn text_preview_view<'a>(
+        &'a self,
+        text_preview: &'a TextPreview,
+        hex_page: usize,
+    ) -> Element<'a, Message> {
+        let (content, encoding, truncated, highlighted) = match text_preview {
+            TextPreview::Text {
+                text,
+                encoding,
+                truncated,
+                highlighted,
+            } => (text, encoding, truncated, highlighted),
+            TextPreview::Binary(dump) => return self.hex_dump_view(dump, hex_page),
+        };
+        let status = if *truncated {
+            format!("{encoding}, first {}", format_size(PREVIEW_BYTES as u64))
+        } else {
+            encoding.to_string()
+        };
         column![
-            self.get_icon(true),
+            text(status)
+                .size(12)
+                .horizontal_alignment(alignment::Horizontal::Center)
+                .width(Length::Fill),
+            scrollable(Self::preview_text(content, highlighted.as_deref())).direction(
+                scrollable::Direction::Both {
+                    vertical: scrollable::Properties::default(),
+                    horizontal: scrollable::Properties::default(),
+                }
+            ),
+        ]
+        .spacing(4)
+        .into()
+    }
+
+    fn right_view<'a>(
+        &'a self,
+        snippet: Option<&'a str>,
+        image_info: Option<&ImageInfo>,
+        text_preview: Option<&'a TextPreview>,
+        hex_page: usize,
+    ) -> Element<'a, Message> {
+        let mut view = column![
+            self.pane_icon(),
             text(self.permission())
                 .horizontal_alignment(alignment::Horizontal::Center)
                 .width(Length::Fill),
+            text(self.metadata_line())
+                .horizontal_alignment(alignment::Horizontal::Center)
+                .width(Length::Fill),
             text(self.name())
                 .horizontal_alignment(alignment::Horizontal::Center)
                 .shaping(text::Shaping::Advanced)
                 .width(Length::Fill)
-        ]
-        .into()
+        ];
+        for line in image_info.map(Self::image_info_lines).unwrap_or_default() {
+            view = view.push(
+                text(line)
+                    .horizontal_alignment(alignment::Horizontal::Center)
+                    .shaping(text::Shaping::Advanced)
+                    .size(14)
+                    .width(Length::Fill),
+            );
+        }
+        // the lines matching a search say more than the start of the file
+        if snippet.is_none() {
+            if let Some(text_preview) = text_preview {
+                view = view
+                    .push(Space::with_height(10.))
+                    .push(self.text_preview_view(text_preview, hex_page));
+            }
+        }
+        if let Some(snippet) = snippet {
+            view = view.push(Space::with_height(10.)).push(
+                scrollable(
+                    text(snippet)
+                        .font(Font::MONOSPACE)
+                        .shaping(text::Shaping::Advanced)
+                        .size(14),
+                )
+                .direction(scrollable::Direction::Both {
+                    vertical: scrollable::Properties::default(),
+                    horizontal: scrollable::Properties::default(),
+                }),
+            );
+        }
+        view.into()
     }
 
+    #[allow(clippy::too_many_arguments)]
     fn view(
         &self,
+        label: String,
+        highlight: Vec<usize>,
         select_dir: bool,
-        preview_image: bool,
+        thumbnail: Option<&PathBuf>,
         current_selected: Option<&PathBuf>,
         is_checked: bool,
-    ) -> Element<Message> {
-        let mut file_btn = button(self.get_icon(preview_image))
+        is_focused: bool,
+        theme: &Theme,
+    ) -> Element<'_, Message> {
+        let mut file_btn = button(self.grid_icon(thumbnail))
             .padding(10)
             .width(BUTTON_WIDTH)
             .height(BUTTON_WIDTH);
@@ -718,48 +1767,65 @@ impl FsInfo {
 
         let dir_can_enter = self.is_dir() && self.is_readable();
 
-        let can_selected = self.is_readable() && (self.is_dir() == select_dir);
+        let can_selected = self.is_selectable(select_dir);
         if dir_can_enter || can_selected {
             file_btn = file_btn.style(theme::Button::Secondary);
         }
 
-        if dir_can_enter {
-            file_btn = file_btn.on_press(Message::RequestEnter(self.path()));
+        // what a click does depends on the held modifiers, see
+        // `Message::RequestItemClicked`
+        if dir_can_enter || (can_selected && self.is_file()) {
+            file_btn = file_btn.on_press(Message::RequestItemClicked(self.path()));
         }
 
         let bottom_text: Element<Message> = if can_selected {
-            if self.is_file() {
-                file_btn = file_btn.on_press(Message::RequestSelect(self.path().clone()));
-            }
-
             if is_selected {
                 file_btn = file_btn.style(theme::Button::Primary);
             }
-            container(
-                checkbox(self.name(), is_checked)
-                    .on_toggle(|checked| {
-                        Message::RequestMultiSelect((checked, self.path().clone()))
-                    })
+            let on_toggle = |checked| Message::RequestMultiSelect((checked, self.path().clone()));
+            if highlight.is_empty() {
+                container(
+                    checkbox(label, is_checked)
+                        .on_toggle(on_toggle)
+                        .width(BUTTON_WIDTH),
+                )
+                .width(Length::Fill)
+                .into()
+            } else {
+                container(
+                    row![
+                        checkbox("", is_checked).on_toggle(on_toggle),
+                        highlighted_label(&label, &highlight, theme)
+                    ]
                     .width(BUTTON_WIDTH),
-            )
-            .width(Length::Fill)
-            .into()
-        } else {
+                )
+                .width(Length::Fill)
+                .into()
+            }
+        } else if highlight.is_empty() {
             container(
-                text(self.name())
+                text(label)
                     .shaping(text::Shaping::Advanced)
                     .width(BUTTON_WIDTH)
                     .horizontal_alignment(alignment::Horizontal::Center),
             )
             .width(Length::Fill)
             .into()
+        } else {
+            container(highlighted_label(&label, &highlight, theme))
+                .width(Length::Fill)
+                .center_x()
+                .into()
         };
 
         let tocontainer = column![file_btn, bottom_text];
-        container(tocontainer)
+        let mut item = container(tocontainer)
             .height(COLUMN_WIDTH)
             .width(Length::Fill)
-            .center_x()
-            .into()
+            .center_x();
+        if is_focused {
+            item = item.style(theme::Container::Custom(Box::new(FocusRingStyle)));
+        }
+        item.into()
     }
 }
@@ -0,0 +1,190 @@
+use std::fmt::Write;
+
+/// Bytes shown per line of the dump.
+const LINE_BYTES: usize = 16;
+
+/// Lines shown per page of the dump.
+const PAGE_LINES: usize = 32;
+
+pub const PAGE_BYTES: usize = LINE_BYTES * PAGE_LINES;
+
+/// Only the start of a file is dumped, enough for the magic of an ISO image.
+pub const DUMP_BYTES: usize = 64 * 1024;
+
+/// File types told by the bytes at an offset, checked in order.
+const MAGIC: &[(usize, &[u8], &str)] = &[
+    (0, b"\x7fELF", "ELF executable or library"),
+    (0, b"MZ", "DOS/Windows executable"),
+    (0, b"\xcf\xfa\xed\xfe", "Mach-O binary"),
+    (
+        0,
+        b"\xca\xfe\xba\xbe",
+        "Java class or Mach-O universal binary",
+    ),
+    (0, b"\0asm", "WebAssembly module"),
+    (0, b"%PDF-", "PDF document"),
+    (0, b"PK\x03\x04", "ZIP archive"),
+    (0, b"PK\x05\x06", "ZIP archive (empty)"),
+    (0, b"\x1f\x8b", "gzip compressed data"),
+    (0, b"\xfd7zXZ\0", "XZ compressed data"),
+    (0, b"\x28\xb5\x2f\xfd", "Zstandard compressed data"),
+    (0, b"BZh", "bzip2 compressed data"),
+    (0, b"\x04\x22\x4d\x18", "LZ4 compressed data"),
+    (0, b"7z\xbc\xaf\x27\x1c", "7-Zip archive"),
+    (0, b"Rar!\x1a\x07", "RAR archive"),
+    (0, b"!<arch>\n", "ar archive or Debian package"),
+    (0, b"\xed\xab\xee\xdb", "RPM package"),
+    (257, b"ustar", "tar archive"),
+    (0, b"hsqs", "SquashFS filesystem"),
+    (0, b"\x27\x05\x19\x56", "U-Boot image"),
+    (0, b"\xd0\x0d\xfe\xed", "Device tree blob"),
+    (0, b"\x3a\xff\x26\xed", "Android sparse image"),
+    (0, b"ANDROID!", "Android boot image"),
+    (0, b"LUKS\xba\xbe", "LUKS encrypted volume"),
+    (0, b"QFI\xfb", "QEMU QCOW image"),
+    (0x8001, b"CD001", "ISO 9660 filesystem"),
+    (0, b"SQLite format 3\0", "SQLite database"),
+    (0, b"\x89PNG\r\n\x1a\n", "PNG image"),
+    (0, b"\xff\xd8\xff", "JPEG image"),
+    (0, b"GIF8", "GIF image"),
+    (0, b"OggS", "Ogg media"),
+    (0, b"fLaC", "FLAC audio"),
+    (0, b"ID3", "MP3 audio"),
+    (0, b"\x1a\x45\xdf\xa3", "Matroska or WebM media"),
+    (4, b"ftyp", "MP4/QuickTime media"),
+    (0, b"wOFF", "WOFF font"),
+    (0, b"wOF2", "WOFF2 font"),
+    (0, b"\0\x01\0\0\0", "TrueType font"),
+    (0, b"OTTO", "OpenType font"),
+    (
+        0,
+        b"\xd0\xcf\x11\xe0\xa1\xb1\x1a\xe1",
+        "Microsoft Office document",
+    ),
+    (0, b"-----BEGIN ", "PEM data"),
+];
+
+/// The start of a file, shown as hex and ASCII a page at a time.
+#[derive(Debug, Clone)]
+pub struct HexDump {
+    bytes: Vec<u8>,
+    /// The size of the whole file, which may go on after `bytes`.
+    pub size: u64,
+    pub kind: Option<&'static str>,
+}
+
+/// The type of a file going by its first bytes, RIFF files by their form
+/// type too.
+fn guess_kind(bytes: &[u8]) -> Option<&'static str> {
+    if bytes.starts_with(b"RIFF") && bytes.len() >= 12 {
+        return Some(match &bytes[8..12] {
+            b"WAVE" => "WAVE audio",
+            b"AVI " => "AVI video",
+            b"WEBP" => "WebP image",
+            _ => "RIFF data",
+        });
+    }
+    MAGIC
+        .iter()
+        .find(|(offset, magic, _)| bytes.get(*offset..).is_some_and(|at| at.starts_with(magic)))
+        .map(|(_, _, kind)| *kind)
+}
+
+impl HexDump {
+    pub fn new(bytes: Vec<u8>, size: u64) -> Self {
+        let kind = guess_kind(&bytes);
+        Self { bytes, size, kind }
+    }
+
+    pub fn shown_bytes(&self) -> usize {
+        self.bytes.len()
+    }
+
+    pub fn pages(&self) -> usize {
+        self.bytes.len().div_ceil(PAGE_BYTES).max(1)
+    }
+
+    /// The lines of page `page` like `hexdump -C` prints them, with the
+    /// offset, the bytes in hex and the printable ones as they are.
+    pub fn page(&self, page: usize) -> String {
+        let start = page.saturating_mul(PAGE_BYTES).min(self.bytes.len());
+        let end = (start + PAGE_BYTES).min(self.bytes.len());
+        let mut dump = String::new();
+        for (index, line) in self.bytes[start..end].chunks(LINE_BYTES).enumerate() {
+            let _ = write!(dump, "{:08x}  ", start + index * LINE_BYTES);
+            for column in 0..LINE_BYTES {
+                match line.get(column) {
+                    Some(byte) => {
+                        let _ = write!(dump, "{byte:02x} ");
+                    }
+                    None => dump.push_str("   "),
+                }
+                if column == LINE_BYTES / 2 - 1 {
+                    dump.push(' ');
+                }
+            }
+            dump.push_str(" |");
+            dump.extend(line.iter().map(|&byte| {
+                if byte.is_ascii_graphic() || byte == b' ' {
+                    byte as char
+                } else {
+                    '.'
+                }
+            }));
+            dump.push_str("|\n");
+        }
+        dump.pop();
+        dump
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lays_out_pages() {
+        let mut bytes = b"Hello, world!\n\0\xff".to_vec();
+        bytes.extend_from_slice(b"ab");
+        let dump = HexDump::new(bytes, 1000);
+        assert_eq!(
+            dump.page(0),
+            "00000000  48 65 6c 6c 6f 2c 20 77  6f 72 6c 64 21 0a 00 ff  |Hello, world!...|\n\
+             00000010  61 62                                             |ab|"
+        );
+        assert_eq!(dump.pages(), 1);
+        assert_eq!(dump.size, 1000);
+    }
+
+    #[test]
+    fn bounds_pages() {
+        let dump = HexDump::new((0..=255).cycle().take(PAGE_BYTES + 20).collect(), 5000);
+        assert_eq!(dump.pages(), 2);
+        assert_eq!(dump.shown_bytes(), PAGE_BYTES + 20);
+        assert_eq!(dump.page(0).lines().count(), PAGE_LINES);
+        let second = dump.page(1);
+        assert_eq!(second.lines().count(), 2);
+        assert!(second.starts_with(&format!("{PAGE_BYTES:08x}  00 01")));
+        assert_eq!(dump.page(2), "");
+        assert_eq!(dump.page(usize::MAX), "");
+
+        let empty = HexDump::new(Vec::new(), 0);
+        assert_eq!(empty.pages(), 1);
+        assert_eq!(empty.page(0), "");
+    }
+
+    #[test]
+    fn guesses_kinds() {
+        assert_eq!(
+            guess_kind(b"\x7fELF\x02"),
+            Some("ELF executable or library")
+        );
+        assert_eq!(guess_kind(b"RIFF\0\0\0\0WEBPVP8 "), Some("WebP image"));
+        assert_eq!(guess_kind(b"RIFF\0\0\0\0"), None);
+        let mut tar = vec![0; 512];
+        tar[257..262].copy_from_slice(b"ustar");
+        assert_eq!(guess_kind(&tar), Some("tar archive"));
+        assert_eq!(guess_kind(&tar[..260]), None);
+        assert_eq!(guess_kind(b"plain text"), None);
+    }
+}
@@ -0,0 +1,113 @@
+use std::path::Path;
+use std::sync::LazyLock;
+
+use iced::Color;
+use mime::Mime;
+use syntect::easy::HighlightLines;
+use syntect::highlighting::ThemeSet;
+use syntect::parsing::{SyntaxDefinition, SyntaxReference, SyntaxSet};
+use syntect::util::LinesWithEndings;
+
+/// Lines after these are shown plain, highlighting is slow and the preview
+/// is made of a widget per span.
+pub const HIGHLIGHT_LINES: usize = 400;
+
+const TOML_SYNTAX: &str = include_str!("../resources/TOML.sublime-syntax");
+
+const LIGHT_THEME: &str = "InspiredGitHub";
+const DARK_THEME: &str = "base16-ocean.dark";
+
+/// The syntaxes bundled with syntect, and TOML which it lacks.
+static SYNTAXES: LazyLock<SyntaxSet> = LazyLock::new(|| {
+    let mut builder = SyntaxSet::load_defaults_newlines().into_builder();
+    if let Ok(toml) = SyntaxDefinition::load_from_str(TOML_SYNTAX, true, Some("TOML")) {
+        builder.add(toml);
+    }
+    builder.build()
+});
+
+static THEMES: LazyLock<ThemeSet> = LazyLock::new(ThemeSet::load_defaults);
+
+/// MIME subtypes whose syntax is not named after them, with the extension
+/// which finds it.
+const MIME_SYNTAXES: &[(&str, &str)] = &[
+    ("x-shellscript", "sh"),
+    ("x-python", "py"),
+    ("x-perl", "pl"),
+    ("x-ruby", "rb"),
+    ("x-php", "php"),
+    ("javascript", "js"),
+    ("x-rust", "rs"),
+    ("rust", "rs"),
+    ("x-yaml", "yaml"),
+    ("x-csrc", "c"),
+    ("x-chdr", "h"),
+    ("x-c++src", "cpp"),
+    ("x-c++hdr", "hpp"),
+    ("x-java", "java"),
+    ("x-go", "go"),
+    ("x-makefile", "make"),
+    ("markdown", "md"),
+    ("x-markdown", "md"),
+];
+
+/// A line as colored spans.
+pub type HighlightedLine = Vec<(Color, String)>;
+
+fn find_syntax(path: &Path, mime_types: &[Mime], text: &str) -> Option<&'static SyntaxReference> {
+    let by_extension = || {
+        let extension = path.extension()?.to_str()?;
+        SYNTAXES.find_syntax_by_extension(extension)
+    };
+    // like Makefile, which syntect lists as an extension
+    let by_name = || SYNTAXES.find_syntax_by_extension(path.file_name()?.to_str()?);
+    let by_mime = || {
+        mime_types.iter().find_map(|mime| {
+            let subtype = mime.subtype().as_str();
+            let token = MIME_SYNTAXES
+                .iter()
+                .find(|(mime_subtype, _)| *mime_subtype == subtype)
+                .map_or(subtype, |(_, extension)| extension);
+            SYNTAXES.find_syntax_by_token(token)
+        })
+    };
+    // a shebang or a modeline
+    let by_first_line = || SYNTAXES.find_syntax_by_first_line(text.lines().next()?);
+    by_extension()
+        .or_else(by_name)
+        .or_else(by_mime)
+        .or_else(by_first_line)
+        .filter(|syntax| syntax.name != "Plain Text")
+}
+
+/// Highlights the first [`HIGHLIGHT_LINES`] of `text` with the colors of a
+/// light or a dark theme, `None` if no syntax is known for the file.
+pub fn highlight(
+    text: &str,
+    path: &Path,
+    mime_types: &[Mime],
+    dark: bool,
+) -> Option<Vec<HighlightedLine>> {
+    let syntax = find_syntax(path, mime_types, text)?;
+    let theme = &THEMES.themes[if dark { DARK_THEME } else { LIGHT_THEME }];
+    let mut highlighter = HighlightLines::new(syntax, theme);
+    let mut lines = Vec::new();
+    for line in LinesWithEndings::from(text).take(HIGHLIGHT_LINES) {
+        let ranges = highlighter.highlight_line(line, &SYNTAXES).ok()?;
+        lines.push(
+            ranges
+                .into_iter()
+                .map(|(style, span)| {
+                    let color = style.foreground;
+                    let span = span.trim_end_matches(['\n', '\r']).replace('\t', "    ");
+                    (
+                        Color::from_rgba8(color.r, color.g, color.b, color.a as f32 / 255.),
+                        span,
+                    )
+                })
+                .filter(|(_, span)| !span.is_empty())
+                .collect(),
+        );
+    }
+    Some(lines)
+}
@@ -0,0 +1,82 @@
+use iced::widget::scrollable::AbsoluteOffset;
+
+use std::path::PathBuf;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Location {
+    Dir(PathBuf),
+    Recent,
+}
+
+/// A location as it was left, to be restored when going back to it.
+#[derive(Debug, Clone)]
+pub struct Visit {
+    pub location: Location,
+    pub scroll: AbsoluteOffset,
+    pub selected: Option<PathBuf>,
+}
+
+/// The back/forward stack, `visits[index]` is the current location.
+#[derive(Debug)]
+pub struct History {
+    visits: Vec<Visit>,
+    index: usize,
+}
+
+impl History {
+    pub fn new(location: Location) -> Self {
+        Self {
+            visits: vec![Visit {
+                location,
+                scroll: AbsoluteOffset::default(),
+                selected: None,
+            }],
+            index: 0,
+        }
+    }
+
+    pub fn can_go_back(&self) -> bool {
+        self.index > 0
+    }
+
+    pub fn can_go_forward(&self) -> bool {
+        self.index + 1 < self.visits.len()
+    }
+
+    /// Remembers how the current location is left.
+    pub fn save(&mut self, scroll: AbsoluteOffset, selected: Option<PathBuf>) {
+        let visit = &mut self.visits[self.index];
+        visit.scroll = scroll;
+        visit.selected = selected;
+    }
+
+    /// Moves to a new location, dropping everything forward of the current one.
+    pub fn visit(&mut self, location: Location) {
+        if self.visits[self.index].location == location {
+            return;
+        }
+        self.visits.truncate(self.index + 1);
+        self.visits.push(Visit {
+            location,
+            scroll: AbsoluteOffset::default(),
+            selected: None,
+        });
+        self.index += 1;
+    }
+
+    pub fn back(&mut self) -> Option<Visit> {
+        if !self.can_go_back() {
+            return None;
+        }
+        self.index -= 1;
+        Some(self.visits[self.index].clone())
+    }
+
+    pub fn forward(&mut self) -> Option<Visit> {
+        if !self.can_go_forward() {
+            return None;
+        }
+        self.index += 1;
+        Some(self.visits[self.index].clone())
+    }
+}
@@ -1,45 +1,203 @@
-use iced::widget::svg;
+use iced::widget::{image, svg};
+use iced::{Element, Length};
+use lru::LruCache;
 
 use std::{
-    collections::HashMap,
-    sync::{Arc, LazyLock, RwLock},
+    fs,
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicU32, Ordering},
+        LazyLock, Mutex,
+    },
+    time::SystemTime,
 };
 
-#[derive(Debug, Hash, PartialEq, Eq)]
+use crate::icon_theme::lookup_icon;
+use crate::image_info::open_oriented;
+use crate::xpm;
+
+/// Least recently used entries are dropped once the handles add up to this.
+const CACHE_BUDGET: usize = 64 * 1024 * 1024;
+
+/// The scale of the window, themed icons are looked up for it.
+static ICON_SCALE: AtomicU32 = AtomicU32::new(1);
+
+/// Icons of the next integer scale are downscaled, rather than blurred up.
+pub fn set_icon_scale(scale_factor: f64) {
+    let scale = scale_factor.ceil().max(1.) as u32;
+    ICON_SCALE.store(scale, Ordering::Relaxed);
+}
+
+#[derive(Debug, Clone, Hash, PartialEq, Eq)]
 pub enum IconKey {
     Text,
     Dir,
-    Path(String),
+    /// An icon of a theme, see [`lookup_icon`].
+    Themed {
+        theme: String,
+        name: String,
+        size: u32,
+        scale: u32,
+    },
+    /// A file shown as its own icon, downscaled to fit `size`. The
+    /// modification time is part of the key so an edited file is reloaded.
+    Preview {
+        path: PathBuf,
+        modified: Option<SystemTime>,
+        size: u32,
+    },
 }
+
+impl IconKey {
+    /// `size` is in logical pixels, the icon is looked up for the scale of
+    /// the window, see [`set_icon_scale`].
+    pub fn themed(theme: &str, name: &str, size: u32) -> Self {
+        Self::Themed {
+            theme: theme.to_string(),
+            name: name.to_string(),
+            size,
+            scale: ICON_SCALE.load(Ordering::Relaxed),
+        }
+    }
+}
+
 const TEXT_IMAGE: &[u8] = include_bytes!("../resources/text-plain.svg");
 
 const DIR_IMAGE: &[u8] = include_bytes!("../resources/inode-directory.svg");
 
-static ICON_CACHE: LazyLock<Arc<RwLock<HashMap<IconKey, svg::Handle>>>> =
-    LazyLock::new(|| Arc::new(RwLock::new(HashMap::new())));
+/// Themes ship icons as SVG, PNG or XPM, the last two are drawn as images.
+#[derive(Debug, Clone)]
+pub enum IconHandle {
+    Svg(svg::Handle),
+    Raster(image::Handle),
+}
+
+impl IconHandle {
+    /// The handle with the bytes it keeps in memory, raster images larger
+    /// than `max_size` either way are downscaled.
+    fn load(path: &Path, max_size: Option<u32>) -> Option<(Self, usize)> {
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some("svg" | "svgz") => {
+                let mem = fs::read(path).ok()?;
+                let bytes = mem.len();
+                Some((Self::Svg(svg::Handle::from_memory(mem)), bytes))
+            }
+            Some("xpm") => {
+                let (width, height, pixels) = xpm::decode(&fs::read_to_string(path).ok()?)?;
+                let bytes = pixels.len();
+                let handle = image::Handle::from_pixels(width, height, pixels);
+                Some((Self::Raster(handle), bytes))
+            }
+            _ => {
+                let mut decoded = open_oriented(path).ok()?;
+                if let Some(size) = max_size {
+                    if decoded.width() > size || decoded.height() > size {
+                        decoded = decoded.thumbnail(size, size);
+                    }
+                }
+                let decoded = decoded.into_rgba8();
+                let (width, height) = decoded.dimensions();
+                let pixels = decoded.into_raw();
+                let bytes = pixels.len();
+                let handle = image::Handle::from_pixels(width, height, pixels);
+                Some((Self::Raster(handle), bytes))
+            }
+        }
+    }
 
-pub fn get_icon_handle(key: IconKey) -> svg::Handle {
-    let icon_cache = ICON_CACHE.read().unwrap();
-    if icon_cache.contains_key(&key) {
-        return icon_cache.get(&key).unwrap().clone();
+    pub fn view<'a, Message>(
+        &self,
+        width: impl Into<Length>,
+        height: impl Into<Length>,
+    ) -> Element<'a, Message> {
+        match self {
+            Self::Svg(handle) => svg(handle.clone()).width(width).height(height).into(),
+            Self::Raster(handle) => image(handle.clone()).width(width).height(height).into(),
+        }
     }
-    drop(icon_cache);
-    let mut icon_cache = ICON_CACHE.write().unwrap();
-    if let IconKey::Path(ref path) = key {
-        if let Ok(mem) = std::fs::read(path) {
-            let handle = svg::Handle::from_memory(mem);
-            icon_cache.insert(key, handle.clone());
-            return handle;
-        };
-        return svg::Handle::from_path(path);
+}
+
+struct IconCache {
+    handles: LruCache<IconKey, (IconHandle, usize)>,
+    /// The size of the handles, kept within [`CACHE_BUDGET`].
+    bytes: usize,
+}
+
+impl IconCache {
+    fn insert(&mut self, key: IconKey, handle: IconHandle, bytes: usize) {
+        if bytes > CACHE_BUDGET {
+            return;
+        }
+        if let Some((_, old)) = self.handles.put(key, (handle, bytes)) {
+            self.bytes -= old;
+        }
+        self.bytes += bytes;
+        while self.bytes > CACHE_BUDGET {
+            let Some((_, (_, evicted))) = self.handles.pop_lru() else {
+                break;
+            };
+            self.bytes -= evicted;
+        }
     }
-    if let IconKey::Text = key {
-        let text_handle = svg::Handle::from_memory(TEXT_IMAGE);
-        icon_cache.insert(key, text_handle.clone());
-        return text_handle;
+}
+
+static ICON_CACHE: LazyLock<Mutex<IconCache>> = LazyLock::new(|| {
+    Mutex::new(IconCache {
+        handles: LruCache::unbounded(),
+        bytes: 0,
+    })
+});
+
+/// The handle if it is loaded already, never loading it.
+pub fn cached_icon_handle(key: &IconKey) -> Option<IconHandle> {
+    let mut icon_cache = ICON_CACHE.lock().unwrap();
+    icon_cache
+        .handles
+        .get(key)
+        .map(|(handle, _)| handle.clone())
+}
+
+pub fn is_cached(key: &IconKey) -> bool {
+    ICON_CACHE.lock().unwrap().handles.contains(key)
+}
+
+/// `None` if a themed icon is not found or a preview can't be decoded.
+pub fn get_icon_handle(key: IconKey) -> Option<IconHandle> {
+    let mut icon_cache = ICON_CACHE.lock().unwrap();
+    if let Some((handle, _)) = icon_cache.handles.get(&key) {
+        return Some(handle.clone());
     }
+    // loading may decode a large image, don't hold up other lookups meanwhile
+    drop(icon_cache);
+    let (handle, bytes) = match &key {
+        IconKey::Text => (
+            IconHandle::Svg(svg::Handle::from_memory(TEXT_IMAGE)),
+            TEXT_IMAGE.len(),
+        ),
+        IconKey::Dir => (
+            IconHandle::Svg(svg::Handle::from_memory(DIR_IMAGE)),
+            DIR_IMAGE.len(),
+        ),
+        IconKey::Themed {
+            theme,
+            name,
+            size,
+            scale,
+        } => IconHandle::load(
+            &lookup_icon(theme, name, *size, *scale)?,
+            Some(size * scale),
+        )?,
+        IconKey::Preview { path, size, .. } => IconHandle::load(path, Some(*size))?,
+    };
+    ICON_CACHE
+        .lock()
+        .unwrap()
+        .insert(key, handle.clone(), bytes);
+    Some(handle)
+}
 
-    let dir_handle = svg::Handle::from_memory(DIR_IMAGE);
-    icon_cache.insert(key, dir_handle.clone());
-    dir_handle
+/// The icons bundled in `resources/`, for when the theme has none.
+pub fn bundled_icon(is_dir: bool) -> IconHandle {
+    let key = if is_dir { IconKey::Dir } else { IconKey::Text };
+    get_icon_handle(key).expect("bundled icons always load")
 }
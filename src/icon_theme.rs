@@ -0,0 +1,306 @@
+use std::collections::{HashMap, HashSet};
+use std::path::PathBuf;
+use std::sync::{Arc, LazyLock, RwLock};
+use std::time::Duration;
+
+use zbus::zvariant::{OwnedValue, Value};
+
+use crate::config::CONFIG;
+use crate::keyfile::KeyFile;
+use crate::utils::{home_dir, xdg_config_home, xdg_data_dirs, xdg_data_home};
+
+/// In the order the Icon Theme spec prefers them.
+const EXTENSIONS: &[&str] = &["png", "svg", "xpm"];
+
+/// Every theme falls back to hicolor, where applications install their icons.
+pub const FALLBACK_THEME: &str = "hicolor";
+
+/// A missing or stuck portal is given up on after this.
+const PORTAL_TIMEOUT: Duration = Duration::from_millis(500);
+
+/// The icon theme of the desktop, see [`detect_icon_theme`] and
+/// [`set_portal_icon_theme`].
+static ICON_THEME: LazyLock<RwLock<String>> = LazyLock::new(|| RwLock::new(detect_icon_theme()));
+
+/// `~/.icons`, then `icons` in each XDG data dir, then `/usr/share/pixmaps`.
+static BASE_DIRS: LazyLock<Vec<PathBuf>> = LazyLock::new(|| {
+    let mut dirs = vec![home_dir().join(".icons"), xdg_data_home().join("icons")];
+    dirs.extend(xdg_data_dirs().into_iter().map(|dir| dir.join("icons")));
+    dirs.push(PathBuf::from("/usr/share/pixmaps"));
+    dirs
+});
+
+static THEMES: LazyLock<RwLock<HashMap<String, Option<Arc<IconTheme>>>>> =
+    LazyLock::new(Default::default);
+
+type LookupKey = (String, String, u32, u32);
+
+static LOOKUPS: LazyLock<RwLock<HashMap<LookupKey, Option<PathBuf>>>> =
+    LazyLock::new(Default::default);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum DirType {
+    Fixed,
+    Scalable,
+    Threshold,
+}
+
+/// One of the `Directories` of an `index.theme`.
+#[derive(Debug)]
+struct ThemeDir {
+    path: String,
+    size: u32,
+    scale: u32,
+    min_size: u32,
+    max_size: u32,
+    threshold: u32,
+    kind: DirType,
+}
+
+impl ThemeDir {
+    fn parse(index: &KeyFile, path: &str) -> Option<Self> {
+        let size = index.get_u32(path, "Size")?;
+        let kind = match index.get(path, "Type") {
+            Some("Fixed") => DirType::Fixed,
+            Some("Scalable") => DirType::Scalable,
+            _ => DirType::Threshold,
+        };
+        Some(Self {
+            path: path.to_string(),
+            size,
+            scale: index.get_u32(path, "Scale").unwrap_or(1),
+            min_size: index.get_u32(path, "MinSize").unwrap_or(size),
+            max_size: index.get_u32(path, "MaxSize").unwrap_or(size),
+            threshold: index.get_u32(path, "Threshold").unwrap_or(2),
+            kind,
+        })
+    }
+
+    fn matches_size(&self, size: u32, scale: u32) -> bool {
+        if self.scale != scale {
+            return false;
+        }
+        match self.kind {
+            DirType::Fixed => self.size == size,
+            DirType::Scalable => (self.min_size..=self.max_size).contains(&size),
+            DirType::Threshold => (self.size.saturating_sub(self.threshold)
+                ..=self.size + self.threshold)
+                .contains(&size),
+        }
+    }
+
+    /// How far the icons of this directory are from the wanted size, in
+    /// device pixels.
+    fn size_distance(&self, size: u32, scale: u32) -> u32 {
+        let wanted = size * scale;
+        let (min, max) = match self.kind {
+            DirType::Fixed => (self.size, self.size),
+            DirType::Scalable => (self.min_size, self.max_size),
+            DirType::Threshold => (
+                self.size.saturating_sub(self.threshold),
+                self.size + self.threshold,
+            ),
+        };
+        let (min, max) = (min * self.scale, max * self.scale);
+        if wanted < min {
+            min - wanted
+        } else {
+            wanted.saturating_sub(max)
+        }
+    }
+}
+
+#[derive(Debug)]
+struct IconTheme {
+    /// The theme directory in each base dir which has one, a theme may be
+    /// spread over several of them.
+    roots: Vec<PathBuf>,
+    dirs: Vec<ThemeDir>,
+    inherits: Vec<String>,
+}
+
+impl IconTheme {
+    fn load(name: &str) -> Option<Self> {
+        let roots: Vec<PathBuf> = BASE_DIRS
+            .iter()
+            .map(|base| base.join(name))
+            .filter(|root| root.is_dir())
+            .collect();
+        let index = roots
+            .iter()
+            .find_map(|root| KeyFile::open(root.join("index.theme")))?;
+        let mut dirs = index.get_list("Icon Theme", "Directories");
+        dirs.extend(index.get_list("Icon Theme", "ScaledDirectories"));
+        Some(Self {
+            roots,
+            dirs: dirs
+                .iter()
+                .filter_map(|dir| ThemeDir::parse(&index, dir))
+                .collect(),
+            inherits: index.get_list("Icon Theme", "Inherits"),
+        })
+    }
+
+    /// An icon of exactly the wanted size, or else the closest one.
+    fn lookup(&self, icon: &str, size: u32, scale: u32) -> Option<PathBuf> {
+        let mut closest: Option<(u32, PathBuf)> = None;
+        for dir in self.dirs.iter() {
+            for root in self.roots.iter() {
+                for extension in EXTENSIONS {
+                    let path = root.join(&dir.path).join(format!("{icon}.{extension}"));
+                    if !path.is_file() {
+                        continue;
+                    }
+                    if dir.matches_size(size, scale) {
+                        return Some(path);
+                    }
+                    let distance = dir.size_distance(size, scale);
+                    if closest.as_ref().is_none_or(|(min, _)| distance < *min) {
+                        closest = Some((distance, path));
+                    }
+                }
+            }
+        }
+        closest.map(|(_, path)| path)
+    }
+}
+
+fn theme(name: &str) -> Option<Arc<IconTheme>> {
+    if let Some(theme) = THEMES.read().unwrap().get(name) {
+        return theme.clone();
+    }
+    let theme = IconTheme::load(name).map(Arc::new);
+    THEMES
+        .write()
+        .unwrap()
+        .insert(name.to_string(), theme.clone());
+    theme
+}
+
+/// Looks in `name` and then in the themes it inherits from.
+fn find_in_theme(
+    name: &str,
+    icon: &str,
+    size: u32,
+    scale: u32,
+    visited: &mut HashSet<String>,
+) -> Option<PathBuf> {
+    // a theme inheriting from itself would loop forever
+    if !visited.insert(name.to_string()) {
+        return None;
+    }
+    let theme = theme(name)?;
+    if let Some(path) = theme.lookup(icon, size, scale) {
+        return Some(path);
+    }
+    theme
+        .inherits
+        .iter()
+        .find_map(|parent| find_in_theme(parent, icon, size, scale, visited))
+}
+
+/// Icons lying directly in a base dir, outside of any theme.
+fn fallback_icon(icon: &str) -> Option<PathBuf> {
+    BASE_DIRS.iter().find_map(|base| {
+        EXTENSIONS
+            .iter()
+            .map(|extension| base.join(format!("{icon}.{extension}")))
+            .find(|path| path.is_file())
+    })
+}
+
+/// Finds `icon` following the freedesktop Icon Theme spec, falling back to
+/// hicolor. The results are cached, so this is cheap to call on every render.
+pub fn lookup_icon(theme: &str, icon: &str, size: u32, scale: u32) -> Option<PathBuf> {
+    let key = (theme.to_string(), icon.to_string(), size, scale);
+    if let Some(path) = LOOKUPS.read().unwrap().get(&key) {
+        return path.clone();
+    }
+    let mut visited = HashSet::new();
+    let path = find_in_theme(theme, icon, size, scale, &mut visited)
+        .or_else(|| find_in_theme(FALLBACK_THEME, icon, size, scale, &mut visited))
+        .or_else(|| fallback_icon(icon));
+    LOOKUPS.write().unwrap().insert(key, path.clone());
+    path
+}
+
+async fn read_portal_setting(namespace: &str, key: &str) -> Option<String> {
+    let connection = zbus::Connection::session().await.ok()?;
+    let body = (namespace, key);
+    let call = |method| {
+        connection.call_method(
+            Some("org.freedesktop.portal.Desktop"),
+            "/org/freedesktop/portal/desktop",
+            Some("org.freedesktop.portal.Settings"),
+            method,
+            &body,
+        )
+    };
+    // `Read` is deprecated and wraps the value in one more variant, but older
+    // portals only have that
+    let value: OwnedValue = match call("ReadOne").await {
+        Ok(reply) => reply.body().deserialize().ok()?,
+        Err(_) => call("Read").await.ok()?.body().deserialize().ok()?,
+    };
+    match Value::from(value) {
+        Value::Value(inner) => String::try_from(*inner).ok(),
+        value => String::try_from(value).ok(),
+    }
+}
+
+/// Asks the settings portal for the icon theme, which takes a round trip
+/// over D-Bus, so it is done once at startup off the UI thread.
+pub async fn portal_icon_theme() -> Option<String> {
+    if CONFIG.icon_theme.is_some() {
+        return None;
+    }
+    tokio::time::timeout(
+        PORTAL_TIMEOUT,
+        read_portal_setting("org.gnome.desktop.interface", "icon-theme"),
+    )
+    .await
+    .ok()?
+}
+
+fn gtk_icon_theme() -> Option<String> {
+    KeyFile::open(xdg_config_home().join("gtk-3.0/settings.ini"))?
+        .get("Settings", "gtk-icon-theme-name")
+        .map(|theme| theme.to_string())
+}
+
+fn kde_icon_theme() -> Option<String> {
+    KeyFile::open(xdg_config_home().join("kdeglobals"))?
+        .get("Icons", "Theme")
+        .map(|theme| theme.to_string())
+}
+
+fn clean_theme_name(theme: String) -> Option<String> {
+    Some(theme.trim_matches(['"', '\'']).to_string()).filter(|theme| !theme.is_empty())
+}
+
+/// The theme set in our config, else the one GTK or KDE report, else
+/// hicolor, until the portal answers.
+fn detect_icon_theme() -> String {
+    CONFIG
+        .icon_theme
+        .clone()
+        .and_then(clean_theme_name)
+        .or_else(|| gtk_icon_theme().and_then(clean_theme_name))
+        .or_else(|| kde_icon_theme().and_then(clean_theme_name))
+        .unwrap_or_else(|| FALLBACK_THEME.to_string())
+}
+
+/// The theme the portal reported, see [`portal_icon_theme`], which goes
+/// before the ones of GTK and KDE but not the one of our config.
+pub fn set_portal_icon_theme(theme: Option<String>) {
+    if CONFIG.icon_theme.is_some() {
+        return;
+    }
+    if let Some(theme) = theme.and_then(clean_theme_name) {
+        *ICON_THEME.write().unwrap() = theme;
+    }
+}
+
+pub fn icon_theme() -> String {
+    ICON_THEME.read().unwrap().clone()
+}
@@ -0,0 +1,291 @@
+use std::fs::File;
+use std::io::{BufReader, Read};
+use std::path::{Path, PathBuf};
+
+use exif::{Exif, In, Tag};
+use image::codecs::{jpeg::JpegDecoder, png::PngDecoder, tiff::TiffDecoder};
+use image::{ColorType, DynamicImage, ImageDecoder, ImageFormat, ImageResult};
+
+/// What the side pane tells about an image, read from its headers only.
+#[derive(Debug, Clone)]
+pub struct ImageInfo {
+    pub width: u32,
+    pub height: u32,
+    pub color: String,
+    pub camera: Option<String>,
+    pub captured: Option<String>,
+    pub has_gps: bool,
+}
+
+fn read_exif(path: &Path) -> Option<Exif> {
+    let mut reader = BufReader::new(File::open(path).ok()?);
+    exif::Reader::new().read_from_container(&mut reader).ok()
+}
+
+fn exif_text(exif: &Exif, tag: Tag) -> Option<String> {
+    let field = exif.get_field(tag, In::PRIMARY)?;
+    let text = field.display_value().to_string();
+    let text = text.trim_matches(|c: char| c == '"' || c.is_whitespace());
+    (!text.is_empty()).then(|| text.to_string())
+}
+
+/// The EXIF orientation, 1 when upright or unknown.
+fn orientation(exif: Option<&Exif>) -> u32 {
+    exif.and_then(|exif| exif.get_field(Tag::Orientation, In::PRIMARY))
+        .and_then(|field| field.value.get_uint(0))
+        .unwrap_or(1)
+}
+
+/// Turns an image decoded as stored upright, as EXIF orientation `orientation`
+/// says it should be seen.
+fn apply_orientation(image: DynamicImage, orientation: u32) -> DynamicImage {
+    match orientation {
+        2 => image.fliph(),
+        3 => image.rotate180(),
+        4 => image.flipv(),
+        5 => image.rotate90().fliph(),
+        6 => image.rotate90(),
+        7 => image.rotate270().fliph(),
+        8 => image.rotate270(),
+        _ => image,
+    }
+}
+
+/// Decodes `path` like `image::open`, turned the way a camera meant it to.
+pub fn open_oriented(path: &Path) -> ImageResult<DynamicImage> {
+    let image = image::open(path)?;
+    Ok(apply_orientation(
+        image,
+        orientation(read_exif(path).as_ref()),
+    ))
+}
+
+fn describe_color(color: ColorType) -> String {
+    let channels = color.channel_count();
+    let name = match (color.has_color(), color.has_alpha()) {
+        (true, true) => "RGBA",
+        (true, false) => "RGB",
+        (false, true) => "Grayscale with alpha",
+        (false, false) => "Grayscale",
+    };
+    format!("{name}, {} bit", color.bits_per_pixel() / channels as u16)
+}
+
+/// The size and color of a WebP from its first chunk, the decoder of the
+/// `image` crate decodes the whole image to tell.
+fn webp_header(path: &Path) -> Option<(u32, u32, String)> {
+    let mut header = [0; 30];
+    File::open(path).ok()?.read_exact(&mut header).ok()?;
+    parse_webp_header(&header)
+}
+
+/// The size and color of a WebP image going by its first chunk header.
+fn parse_webp_header(header: &[u8]) -> Option<(u32, u32, String)> {
+    let header = header.get(..30)?;
+    if &header[0..4] != b"RIFF" || &header[8..12] != b"WEBP" {
+        return None;
+    }
+    let data = &header[20..];
+    let u24 = |bytes: &[u8]| u32::from_le_bytes([bytes[0], bytes[1], bytes[2], 0]);
+    match &header[12..16] {
+        b"VP8X" => {
+            let alpha = data[0] & 0x10 != 0;
+            let color = if alpha { "RGBA, 8 bit" } else { "RGB, 8 bit" };
+            Some((
+                u24(&data[4..7]) + 1,
+                u24(&data[7..10]) + 1,
+                color.to_string(),
+            ))
+        }
+        b"VP8L" => {
+            let bits = u32::from_le_bytes([data[1], data[2], data[3], data[4]]);
+            let alpha = bits & (1 << 28) != 0;
+            let color = if alpha { "RGBA, 8 bit" } else { "RGB, 8 bit" };
+            Some((
+                (bits & 0x3fff) + 1,
+                ((bits >> 14) & 0x3fff) + 1,
+                color.to_string(),
+            ))
+        }
+        b"VP8 " => {
+            let width = u16::from_le_bytes([data[6], data[7]]) & 0x3fff;
+            let height = u16::from_le_bytes([data[8], data[9]]) & 0x3fff;
+            Some((width as u32, height as u32, "RGB, 8 bit".to_string()))
+        }
+        _ => None,
+    }
+}
+
+fn header_info(path: &Path) -> Option<(u32, u32, String)> {
+    let format = ImageFormat::from_path(path).ok()?;
+    let reader = || File::open(path).ok().map(BufReader::new);
+    let (width, height, color) = match format {
+        ImageFormat::Jpeg => {
+            let decoder = JpegDecoder::new(reader()?).ok()?;
+            let (width, height) = decoder.dimensions();
+            (width, height, decoder.color_type())
+        }
+        ImageFormat::Png => {
+            let decoder = PngDecoder::new(reader()?).ok()?;
+            let (width, height) = decoder.dimensions();
+            (width, height, decoder.color_type())
+        }
+        ImageFormat::Tiff => {
+            let decoder = TiffDecoder::new(reader()?).ok()?;
+            let (width, height) = decoder.dimensions();
+            (width, height, decoder.color_type())
+        }
+        ImageFormat::WebP => return webp_header(path),
+        _ => return None,
+    };
+    Some((width, height, describe_color(color)))
+}
+
+/// Reads the headers of a JPEG, PNG, WebP or TIFF image off the UI thread.
+pub async fn read_image_info(path: PathBuf) -> (PathBuf, Option<ImageInfo>) {
+    let info = tokio::task::spawn_blocking({
+        let path = path.clone();
+        move || {
+            let (mut width, mut height, color) = header_info(&path)?;
+            let exif = read_exif(&path);
+            // the size as shown, which is turned by a quarter for some
+            if orientation(exif.as_ref()) >= 5 {
+                std::mem::swap(&mut width, &mut height);
+            }
+            let camera = exif.as_ref().and_then(|exif| {
+                let model = exif_text(exif, Tag::Model)?;
+                match exif_text(exif, Tag::Make) {
+                    // models tend to repeat the make, like "Canon EOS R5"
+                    Some(make) if !model.starts_with(&make) => Some(format!("{make} {model}")),
+                    _ => Some(model),
+                }
+            });
+            let captured = exif.as_ref().and_then(|exif| {
+                exif_text(exif, Tag::DateTimeOriginal).or_else(|| exif_text(exif, Tag::DateTime))
+            });
+            let has_gps = exif
+                .as_ref()
+                .is_some_and(|exif| exif.get_field(Tag::GPSLatitude, In::PRIMARY).is_some());
+            Some(ImageInfo {
+                width,
+                height,
+                color,
+                camera,
+                captured,
+                has_gps,
+            })
+        }
+    })
+    .await
+    .ok()
+    .flatten();
+    (path, info)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{GenericImageView, GrayImage, Luma};
+
+    #[test]
+    fn applies_orientations() {
+        let (width, height) = (2, 3);
+        let image = GrayImage::from_fn(width, height, |x, y| Luma([(y * width + x) as u8]));
+        let image = DynamicImage::ImageLuma8(image);
+        // where each orientation puts the stored pixel (x, y)
+        let moved = |orientation, x, y| match orientation {
+            2 => (1 - x, y),
+            3 => (1 - x, 2 - y),
+            4 => (x, 2 - y),
+            5 => (y, x),
+            6 => (2 - y, x),
+            7 => (2 - y, 1 - x),
+            8 => (y, 1 - x),
+            _ => (x, y),
+        };
+        for orientation in 0..=8 {
+            let turned = apply_orientation(image.clone(), orientation);
+            let turned_size = if orientation >= 5 {
+                (height, width)
+            } else {
+                (width, height)
+            };
+            assert_eq!(
+                turned.dimensions(),
+                turned_size,
+                "orientation {orientation}"
+            );
+            for (x, y, pixel) in image.pixels() {
+                let (turned_x, turned_y) = moved(orientation, x, y);
+                assert_eq!(
+                    turned.get_pixel(turned_x, turned_y),
+                    pixel,
+                    "orientation {orientation} at {x}, {y}"
+                );
+            }
+        }
+    }
+
+    fn webp(chunk: &[u8; 4], data: &[u8]) -> Vec<u8> {
+        let mut header = b"RIFF\0\0\0\0WEBP".to_vec();
+        header.extend_from_slice(chunk);
+        header.extend_from_slice(&[0; 4]);
+        header.extend_from_slice(data);
+        header.resize(30, 0);
+        header
+    }
+
+    #[test]
+    fn parses_webp_headers() {
+        // lossy: the frame tag and start code, then 14 bit sizes
+        let lossy = webp(
+            b"VP8 ",
+            &[0, 0, 0, 0x9d, 0x01, 0x2a, 0x40, 0x01, 0xf0, 0xc0],
+        );
+        assert_eq!(
+            parse_webp_header(&lossy),
+            Some((320, 240, "RGB, 8 bit".to_string()))
+        );
+
+        // lossless: the signature, then 14 bit sizes less one and the alpha bit
+        let bits: u32 = 319 | (239 << 14) | (1 << 28);
+        let mut data = vec![0x2f];
+        data.extend_from_slice(&bits.to_le_bytes());
+        let lossless = webp(b"VP8L", &data);
+        assert_eq!(
+            parse_webp_header(&lossless),
+            Some((320, 240, "RGBA, 8 bit".to_string()))
+        );
+
+        // extended: the flags, then 24 bit sizes less one
+        let extended = webp(b"VP8X", &[0, 0, 0, 0, 0xff, 0x0f, 0, 0x0f, 0x0a, 0]);
+        assert_eq!(
+            parse_webp_header(&extended),
+            Some((4096, 2576, "RGB, 8 bit".to_string()))
+        );
+        let extended = webp(b"VP8X", &[0x10, 0, 0, 0, 0, 0, 0, 0, 0, 0]);
+        assert_eq!(
+            parse_webp_header(&extended),
+            Some((1, 1, "RGBA, 8 bit".to_string()))
+        );
+    }
+
+    #[test]
+    fn rejects_broken_webp_headers() {
+        let header = webp(b"VP8X", &[0; 10]);
+        for len in 0..header.len() {
+            assert_eq!(parse_webp_header(&header[..len]), None);
+        }
+        assert_eq!(parse_webp_header(&webp(b"ALPH", &[0; 10])), None);
+        let mut not_webp = header.clone();
+        not_webp[8..12].copy_from_slice(b"WAVE");
+        assert_eq!(parse_webp_header(&not_webp), None);
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("short.webp");
+        std::fs::write(&path, &header[..20]).unwrap();
+        assert_eq!(webp_header(&path), None);
+        std::fs::write(&path, &header).unwrap();
+        assert_eq!(webp_header(&path), Some((1, 1, "RGB, 8 bit".to_string())));
+    }
+}
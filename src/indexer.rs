@@ -0,0 +1,450 @@
+use iced::futures::channel::mpsc;
+use iced::futures::SinkExt;
+use iced::subscription;
+use iced::Subscription;
+
+use inotify::{EventMask, Inotify, WatchDescriptor, WatchMask};
+
+use std::collections::{BTreeSet, HashMap};
+use std::ffi::OsStr;
+use std::fs;
+use std::io::ErrorKind;
+use std::os::unix::ffi::OsStrExt;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{LazyLock, Once, RwLock};
+use std::time::{Duration, Instant};
+
+use crate::config::{IndexConfig, CONFIG};
+use crate::dirfs::{read_fs_info, FsInfo};
+use crate::portal_option::FileFilter;
+use crate::search::{SearchKind, MAX_SEARCH_RESULTS};
+use crate::utils::{write_atomic, xdg_cache_home};
+use crate::Message;
+
+const INDEX_MAGIC: &[u8] = b"iced-filechooser-index-1\n";
+
+/// Changes from inotify are written back to disk at most this often.
+const SAVE_INTERVAL: Duration = Duration::from_secs(30);
+
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+static INDEX: LazyLock<RwLock<BTreeSet<PathBuf>>> = LazyLock::new(Default::default);
+
+static INDEXER: Once = Once::new();
+
+static CRAWLING: AtomicBool = AtomicBool::new(false);
+
+/// Where the index is kept between runs.
+pub fn index_path() -> PathBuf {
+    xdg_cache_home().join("iced-filechooser/index")
+}
+
+/// Whether the first crawl of the roots is still running, so the index may
+/// miss files.
+pub fn is_crawling() -> bool {
+    CRAWLING.load(Ordering::Relaxed)
+}
+
+fn write_varint(out: &mut Vec<u8>, mut value: usize) {
+    while value >= 0x80 {
+        out.push((value as u8) | 0x80);
+        value >>= 7;
+    }
+    out.push(value as u8);
+}
+
+fn read_varint(bytes: &[u8], pos: &mut usize) -> Option<usize> {
+    let mut value = 0;
+    let mut shift = 0;
+    loop {
+        let byte = *bytes.get(*pos)?;
+        *pos += 1;
+        value |= ((byte & 0x7f) as usize) << shift;
+        if byte & 0x80 == 0 {
+            return Some(value);
+        }
+        shift += 7;
+        if shift > 63 {
+            return None;
+        }
+    }
+}
+
+/// The paths are sorted, so each one only stores the bytes it does not
+/// share with the previous path.
+fn encode(paths: &BTreeSet<PathBuf>) -> Vec<u8> {
+    let mut out = INDEX_MAGIC.to_vec();
+    let mut previous: &[u8] = &[];
+    for path in paths {
+        let bytes = path.as_os_str().as_bytes();
+        let shared = previous
+            .iter()
+            .zip(bytes)
+            .take_while(|(a, b)| a == b)
+            .count();
+        write_varint(&mut out, shared);
+        write_varint(&mut out, bytes.len() - shared);
+        out.extend_from_slice(&bytes[shared..]);
+        previous = bytes;
+    }
+    out
+}
+
+fn decode(bytes: &[u8]) -> Option<BTreeSet<PathBuf>> {
+    let mut pos = INDEX_MAGIC.len();
+    if bytes.get(..pos)? != INDEX_MAGIC {
+        return None;
+    }
+    let mut paths = BTreeSet::new();
+    let mut previous: Vec<u8> = Vec::new();
+    while pos < bytes.len() {
+        let shared = read_varint(bytes, &mut pos)?;
+        let len = read_varint(bytes, &mut pos)?;
+        // a corrupt index may hold any length
+        let suffix = bytes.get(pos..pos.checked_add(len)?)?;
+        pos += len;
+        previous.truncate(shared);
+        previous.extend_from_slice(suffix);
+        paths.insert(PathBuf::from(OsStr::from_bytes(&previous)));
+    }
+    Some(paths)
+}
+
+fn save() {
+    let content = encode(&INDEX.read().unwrap());
+    let _ = write_atomic(&index_path(), &content);
+}
+
+fn is_excluded(name: &OsStr, config: &IndexConfig) -> bool {
+    let name = name.to_string_lossy();
+    (!config.index_hidden && name.starts_with('.'))
+        || config
+            .excludes
+            .iter()
+            .any(|exclude| glob::Pattern::new(exclude).is_ok_and(|p| p.matches(&name)))
+}
+
+const WATCH_MASK: WatchMask = WatchMask::CREATE
+    .union(WatchMask::DELETE)
+    .union(WatchMask::MOVED_FROM)
+    .union(WatchMask::MOVED_TO)
+    .union(WatchMask::ONLYDIR)
+    .union(WatchMask::DONT_FOLLOW);
+
+struct Watcher {
+    inotify: Option<Inotify>,
+    watches: HashMap<WatchDescriptor, PathBuf>,
+}
+
+impl Watcher {
+    fn watch(&mut self, dir: &Path) {
+        let Some(inotify) = self.inotify.as_mut() else {
+            return;
+        };
+        // running out of watches only makes the index go stale, it can
+        // still be searched
+        if let Ok(wd) = inotify.watches().add(dir, WATCH_MASK) {
+            self.watches.insert(wd, dir.to_path_buf());
+        }
+    }
+
+    /// Collects everything below `root`, watching each directory on the way.
+    fn crawl(&mut self, root: &Path, config: &IndexConfig, paths: &mut BTreeSet<PathBuf>) {
+        let mut pending = vec![root.to_path_buf()];
+        while let Some(dir) = pending.pop() {
+            self.watch(&dir);
+            let Ok(entries) = fs::read_dir(&dir) else {
+                continue;
+            };
+            for entry in entries.flatten() {
+                if is_excluded(&entry.file_name(), config) {
+                    continue;
+                }
+                let path = entry.path();
+                if entry.file_type().is_ok_and(|file_type| file_type.is_dir()) {
+                    pending.push(path.clone());
+                }
+                paths.insert(path);
+            }
+        }
+    }
+
+    fn crawl_roots(&mut self, config: &IndexConfig) {
+        CRAWLING.store(true, Ordering::Relaxed);
+        let mut paths = BTreeSet::new();
+        for root in config.roots.iter() {
+            self.crawl(root, config, &mut paths);
+        }
+        *INDEX.write().unwrap() = paths;
+        CRAWLING.store(false, Ordering::Relaxed);
+        save();
+    }
+
+    /// Applies pending inotify events to the index, returns whether it changed.
+    fn apply_events(&mut self, config: &IndexConfig) -> bool {
+        let mut buffer = [0; 4096];
+        let mut changed = false;
+        loop {
+            let Some(inotify) = self.inotify.as_mut() else {
+                return changed;
+            };
+            let events = match inotify.read_events(&mut buffer) {
+                Ok(events) => events,
+                Err(e) if e.kind() == ErrorKind::WouldBlock => return changed,
+                Err(_) => {
+                    self.inotify = None;
+                    return changed;
+                }
+            };
+            let mut created = Vec::new();
+            let mut removed = Vec::new();
+            let mut overflowed = false;
+            for event in events {
+                if event.mask.contains(EventMask::Q_OVERFLOW) {
+                    overflowed = true;
+                    continue;
+                }
+                if event.mask.contains(EventMask::IGNORED) {
+                    self.watches.remove(&event.wd);
+                    continue;
+                }
+                let (Some(dir), Some(name)) = (self.watches.get(&event.wd), event.name) else {
+                    continue;
+                };
+                if is_excluded(name, config) {
+                    continue;
+                }
+                let path = dir.join(name);
+                if event
+                    .mask
+                    .intersects(EventMask::CREATE | EventMask::MOVED_TO)
+                {
+                    created.push((path, event.mask.contains(EventMask::ISDIR)));
+                } else if event
+                    .mask
+                    .intersects(EventMask::DELETE | EventMask::MOVED_FROM)
+                {
+                    removed.push(path);
+                }
+            }
+            if overflowed {
+                self.crawl_roots(config);
+                return false;
+            }
+            for path in removed {
+                // a moved directory keeps its watches, which would now
+                // report the old path
+                let stale: Vec<WatchDescriptor> = self
+                    .watches
+                    .iter()
+                    .filter(|(_, dir)| dir.starts_with(&path))
+                    .map(|(wd, _)| wd.clone())
+                    .collect();
+                for wd in stale {
+                    self.watches.remove(&wd);
+                    if let Some(inotify) = self.inotify.as_mut() {
+                        let _ = inotify.watches().remove(wd);
+                    }
+                }
+                let mut index = INDEX.write().unwrap();
+                let below: Vec<PathBuf> = index
+                    .range(path.clone()..)
+                    .take_while(|indexed| indexed.starts_with(&path))
+                    .cloned()
+                    .collect();
+                for indexed in below {
+                    index.remove(&indexed);
+                }
+                changed = true;
+            }
+            for (path, is_dir) in created {
+                let mut paths = BTreeSet::from([path.clone()]);
+                if is_dir {
+                    self.crawl(&path, config, &mut paths);
+                }
+                INDEX.write().unwrap().append(&mut paths);
+                changed = true;
+            }
+        }
+    }
+
+    fn run(mut self, config: &IndexConfig) {
+        if let Some(paths) = fs::read(index_path()).ok().and_then(|bytes| decode(&bytes)) {
+            *INDEX.write().unwrap() = paths;
+        }
+        self.crawl_roots(config);
+        let mut last_save = Instant::now();
+        let mut dirty = false;
+        while self.inotify.is_some() {
+            dirty |= self.apply_events(config);
+            if dirty && last_save.elapsed() >= SAVE_INTERVAL {
+                save();
+                last_save = Instant::now();
+                dirty = false;
+            }
+            std::thread::sleep(POLL_INTERVAL);
+        }
+        if dirty {
+            save();
+        }
+    }
+}
+
+/// Loads the index from disk and keeps it current in a background thread.
+///
+/// Only the first call starts the indexer.
+pub fn start() {
+    INDEXER.call_once(|| {
+        CRAWLING.store(true, Ordering::Relaxed);
+        let watcher = Watcher {
+            inotify: Inotify::init().ok(),
+            watches: HashMap::new(),
+        };
+        std::thread::spawn(move || watcher.run(&CONFIG.index));
+    });
+}
+
+fn search(kind: &SearchKind, filter: &FileFilter, show_hide: bool) -> Vec<FsInfo> {
+    let index = INDEX.read().unwrap();
+    let candidates: Vec<&PathBuf> = match kind {
+        SearchKind::Name(query) => index
+            .iter()
+            .filter(|path| {
+                path.file_name()
+                    .and_then(|name| name.to_str())
+                    .is_some_and(|name| query.matches_name(name))
+            })
+            .collect(),
+        SearchKind::Fuzzy(query) => {
+            let mut scored: Vec<(i64, &PathBuf)> = index
+                .iter()
+                .filter_map(|path| {
+                    let name = path.file_name()?.to_str()?;
+                    Some((query.fuzzy_score(name)?, path))
+                })
+                .collect();
+            scored.sort_by_key(|(score, _)| std::cmp::Reverse(*score));
+            scored.into_iter().map(|(_, path)| path).collect()
+        }
+        SearchKind::Content(_) => Vec::new(),
+    };
+    let mut results = Vec::new();
+    for path in candidates {
+        let Some(name) = path.file_name().and_then(|name| name.to_str()) else {
+            continue;
+        };
+        if !show_hide && name.starts_with('.') {
+            continue;
+        }
+        // files removed since they were indexed are skipped here
+        let Ok(metadata) = fs::symlink_metadata(path) else {
+            continue;
+        };
+        let Some(info) = read_fs_info(path.clone(), name.to_string(), &metadata) else {
+            continue;
+        };
+        let is_match = match kind {
+            SearchKind::Name(query) => query.matches(&info),
+            SearchKind::Fuzzy(query) => query.fuzzy_match(&info).is_some(),
+            SearchKind::Content(_) => false,
+        };
+        if !is_match || !info.is_match_filefilter(filter) {
+            continue;
+        }
+        results.push(info);
+        if results.len() >= MAX_SEARCH_RESULTS {
+            break;
+        }
+    }
+    results
+}
+
+/// Queries the index, waiting for the first crawl if nothing is indexed yet.
+pub fn index_search(
+    id: u64,
+    kind: SearchKind,
+    show_hide: bool,
+    filter: FileFilter,
+) -> Subscription<Message> {
+    subscription::channel(
+        (std::any::TypeId::of::<Watcher>(), id),
+        100,
+        move |mut output: mpsc::Sender<Message>| async move {
+            start();
+            while INDEX.read().unwrap().is_empty() && is_crawling() {
+                tokio::time::sleep(POLL_INTERVAL).await;
+            }
+            let results = tokio::task::spawn_blocking(move || search(&kind, &filter, show_hide))
+                .await
+                .unwrap_or_default();
+            let _ = output
+                .send(Message::SearchResultsFound((
+                    id,
+                    results.into_iter().map(|info| (info, None)).collect(),
+                )))
+                .await;
+            let _ = output.send(Message::SearchFinished(id)).await;
+            loop {
+                std::future::pending::<()>().await;
+            }
+        },
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn paths() -> BTreeSet<PathBuf> {
+        [
+            "/home/user",
+            "/home/user/Documents",
+            "/home/user/Documents/notes.txt",
+            "/home/user/Pictures/été.png",
+            "/srv",
+        ]
+        .into_iter()
+        .map(PathBuf::from)
+        .collect()
+    }
+
+    #[test]
+    fn roundtrips() {
+        let paths = paths();
+        assert_eq!(decode(&encode(&paths)), Some(paths));
+        assert_eq!(decode(&encode(&BTreeSet::new())), Some(BTreeSet::new()));
+    }
+
+    #[test]
+    fn shares_prefixes() {
+        let encoded = encode(&paths());
+        let total: usize = paths().iter().map(|path| path.as_os_str().len()).sum();
+        assert!(encoded.len() - INDEX_MAGIC.len() < total);
+    }
+
+    #[test]
+    fn rejects_truncated_input() {
+        let encoded = encode(&paths());
+        for len in 0..encoded.len() {
+            // cut within the magic, a varint or a suffix, or between paths
+            if let Some(decoded) = decode(&encoded[..len]) {
+                assert!(decoded.is_subset(&paths()));
+            }
+        }
+        assert_eq!(decode(b"not an index"), None);
+    }
+
+    #[test]
+    fn rejects_corrupt_lengths() {
+        let mut encoded = INDEX_MAGIC.to_vec();
+        write_varint(&mut encoded, 0);
+        write_varint(&mut encoded, usize::MAX);
+        encoded.extend_from_slice(b"/a");
+        assert_eq!(decode(&encoded), None);
+        // a varint which never ends
+        let mut encoded = INDEX_MAGIC.to_vec();
+        encoded.extend_from_slice(&[0xff; 12]);
+        assert_eq!(decode(&encoded), None);
+    }
+}
@@ -0,0 +1,75 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+/// A parsed freedesktop key file, the ini like format of `.desktop` files,
+/// `index.theme` and our own config.
+#[derive(Debug, Clone, Default)]
+pub struct KeyFile {
+    groups: HashMap<String, HashMap<String, String>>,
+}
+
+impl KeyFile {
+    pub fn parse(content: &str) -> Self {
+        let mut groups: HashMap<String, HashMap<String, String>> = HashMap::new();
+        let mut current: Option<String> = None;
+        for line in content.lines() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') || line.starts_with(';') {
+                continue;
+            }
+            if let Some(group) = line
+                .strip_prefix('[')
+                .and_then(|line| line.strip_suffix(']'))
+            {
+                groups.entry(group.to_string()).or_default();
+                current = Some(group.to_string());
+                continue;
+            }
+            let (Some(group), Some((key, value))) = (&current, line.split_once('=')) else {
+                continue;
+            };
+            groups
+                .entry(group.clone())
+                .or_default()
+                .insert(key.trim().to_string(), value.trim().to_string());
+        }
+        Self { groups }
+    }
+
+    pub fn open<P: AsRef<Path>>(path: P) -> Option<Self> {
+        fs::read_to_string(path)
+            .ok()
+            .map(|content| Self::parse(&content))
+    }
+
+    pub fn get(&self, group: &str, key: &str) -> Option<&str> {
+        self.groups.get(group)?.get(key).map(|value| value.as_str())
+    }
+
+    pub fn get_bool(&self, group: &str, key: &str) -> Option<bool> {
+        match self.get(group, key)? {
+            "true" | "1" => Some(true),
+            "false" | "0" => Some(false),
+            _ => None,
+        }
+    }
+
+    pub fn get_u32(&self, group: &str, key: &str) -> Option<u32> {
+        self.get(group, key)?.parse().ok()
+    }
+
+    /// Lists may be separated by `;` like desktop files or by `,` like
+    /// `index.theme`.
+    pub fn get_list(&self, group: &str, key: &str) -> Vec<String> {
+        let Some(value) = self.get(group, key) else {
+            return Vec::new();
+        };
+        value
+            .split([';', ','])
+            .map(|item| item.trim())
+            .filter(|item| !item.is_empty())
+            .map(|item| item.to_string())
+            .collect()
+    }
+}
@@ -1,13 +1,51 @@
+mod bookmarks;
+mod config;
 mod dirfs;
+mod hex_dump;
+mod highlight;
+mod history;
 mod icon_cache;
+mod icon_theme;
+mod image_info;
+mod indexer;
+mod keyfile;
+mod location;
+mod mounts;
+mod places;
 pub mod portal_option;
+mod previews;
+mod query;
+mod recent;
+mod rubber_band;
+mod search;
+mod text_preview;
+mod thumbnailers;
+mod thumbnails;
 mod utils;
+mod xpm;
 
-use dirfs::{update_dir_infos, DirUnit, FsInfo};
-use iced::widget::{checkbox, column, combo_box, container, row, scrollable, text, Column, Space};
+use icon_cache::IconKey;
+use image_info::{read_image_info, ImageInfo};
+use text_preview::{read_text_preview, TextPreview};
+
+use dirfs::{
+    read_fs_info, update_dir_infos, DirUnit, FocusMove, FsInfo, GRID_SCROLL_ID, LOCATION_INPUT_ID,
+};
+use history::{History, Location, Visit};
+use iced::advanced::widget::{
+    self, operation::focusable::Focusable, operation::Outcome, Operation,
+};
+use iced::clipboard;
+use iced::widget::scrollable::AbsoluteOffset;
+use iced::widget::text_input;
+use iced::widget::{
+    button, checkbox, column, combo_box, container, row, scrollable, text, Column, Space,
+};
 use iced::window::Id;
+use iced::{event, keyboard, Event};
 use iced::{executor, Length};
-use iced::{Command, Element, Theme};
+use iced::{Command, Element, Subscription, Theme};
+use location::{complete_location, expand_location};
 use std::path::{Path, PathBuf};
 
 use iced_layershell::Application;
@@ -15,16 +53,40 @@ use iced_runtime::command::Action;
 use iced_runtime::window::Action as WindowAction;
 
 use iced_aw::{split, Split};
+use mounts::Volume;
+use places::Places;
 use portal_option::{FileChosen, FileFilter};
+use search::SearchScope;
+use std::time::Duration;
+use thumbnails::ThumbnailSize;
+
+const VOLUME_POLL_INTERVAL: Duration = Duration::from_secs(2);
 
 #[derive(Debug)]
 pub struct FileChooser {
     dir: DirUnit,
+    /// The scale the window is drawn at, from `GDK_SCALE`.
+    scale_factor: f64,
     display_name: String,
     showhide: bool,
     preview_big_image: bool,
+    show_sidebar: bool,
+    places: Places,
+    history: History,
+    /// Where the grid is scrolled to, saved in the history when leaving.
+    scroll_offset: AbsoluteOffset,
+    /// Applied once the entries of a location returned to are loaded.
+    pending_scroll: Option<AbsoluteOffset>,
     selected_paths: Vec<PathBuf>,
     current_selected: Option<PathBuf>,
+    /// The glob or MIME type of "Select matching".
+    select_pattern: String,
+    modifiers: keyboard::Modifiers,
+    /// Where a Shift-click range starts, the last entry clicked.
+    selection_anchor: Option<PathBuf>,
+    /// The selection before a rubber band drag, which the dragged over
+    /// entries are added to.
+    rubber_band_base: Option<Vec<PathBuf>>,
     right_splitter: Option<u16>,
     left_splitter: Option<u16>,
     choose_option: FileChosen,
@@ -47,13 +109,67 @@ pub enum Message {
     RequestMultiSelect((bool, PathBuf)),
     RequestNextDirs((Vec<FsInfo>, PathBuf)),
     RequestSelect(PathBuf),
+    /// A click on an entry of the grid, which enters, selects, toggles or
+    /// selects a range depending on the held modifiers.
+    RequestItemClicked(PathBuf),
+    RequestRubberBandSelect(Vec<PathBuf>),
+    RubberBandFinished,
+    ModifiersChanged(keyboard::Modifiers),
     RequestEnter(PathBuf),
+    RequestGoBack,
+    RequestGoForward,
+    RequestGridScrolled(AbsoluteOffset),
+    RequestMoveFocus(FocusMove),
+    /// A move of the keyboard focus, and whether a text input had the focus
+    /// when it was asked for.
+    MoveFocus((FocusMove, bool)),
+    RequestTypeAhead(String),
+    RequestActivateFocused,
+    RequestToggleFocused,
+    RequestSelectAll,
+    SelectPatternChanged(String),
+    RequestSelectMatching,
+    RequestInvertSelection,
+    RequestClearSelection,
+    RequestGoUp,
+    RequestToggleShowHide,
+    RequestEditLocation(bool),
+    RequestCompleteLocation,
+    RequestPasteLocation,
+    LocationInputChanged(String),
+    LocationPasted(String),
+    LocationSubmitted,
+    RequestRecent,
+    RecentFilesLoaded(Vec<FsInfo>),
+    /// An image and its thumbnail, `None` if it could not be made.
+    ThumbnailLoaded((PathBuf, Option<PathBuf>)),
+    /// A preview was loaded into the icon cache, or failed to when `true`.
+    PreviewLoaded((IconKey, bool)),
+    ImageInfoLoaded((PathBuf, Option<ImageInfo>)),
+    TextPreviewLoaded((PathBuf, Option<TextPreview>)),
+    /// A page of the hex dump of a file.
+    RequestHexPage((PathBuf, usize)),
     RequestShowHide(bool),
     RequestShowImage(bool),
+    RequestShowSidebar(bool),
+    RequestAddBookmark(PathBuf),
+    RequestRemoveBookmark(usize),
+    RequestRenameBookmark((usize, String)),
+    RequestMoveBookmark((usize, usize)),
+    RequestEditBookmarks(bool),
+    RequestRefreshVolumes,
+    VolumesLoaded(Option<Vec<Volume>>),
+    IconThemeDetected(Option<String>),
     RequestAdjustRightSplitter(u16),
     RequestAdjustLeftSplitter(u16),
     SearchPatternCachedChanged(String),
     SearchPatternChanged,
+    RequestSearchScope(SearchScope),
+    RequestSearchContents(bool),
+    RequestFuzzySearch(bool),
+    RequestCancelSearch,
+    SearchResultsFound((u64, Vec<(FsInfo, Option<String>)>)),
+    SearchFinished(u64),
 
     FilterChanged(FileFilter),
     // CONFIRM
@@ -68,27 +184,59 @@ impl Application for FileChooser {
     type Theme = Theme;
 
     fn new(choose_option: Self::Flags) -> (Self, Command<Message>) {
+        if config::CONFIG.index.enabled {
+            indexer::start();
+        }
         let mut filters = [FileFilter::default()].to_vec();
         let mut input_filters = choose_option.filters().to_vec();
         filters.append(&mut input_filters);
+        let current_dir = std::env::current_dir().unwrap();
+        // the scale of the output is not passed on by the layer shell, so
+        // follow the variable GTK dialogs scale by
+        let scale_factor = std::env::var("GDK_SCALE")
+            .ok()
+            .and_then(|scale| scale.parse::<f64>().ok())
+            .filter(|scale| *scale >= 1.)
+            .unwrap_or(1.);
+        icon_cache::set_icon_scale(scale_factor);
+        let mut chooser = Self {
+            dir: DirUnit::enter(current_dir.as_path()),
+            scale_factor,
+            display_name: choose_option.accept_label().to_string(),
+            showhide: false,
+            preview_big_image: false,
+            show_sidebar: true,
+            places: Places::load(),
+            history: History::new(Location::Dir(current_dir)),
+            scroll_offset: AbsoluteOffset::default(),
+            pending_scroll: None,
+            selected_paths: Vec::new(),
+            current_selected: None,
+            select_pattern: String::new(),
+            modifiers: keyboard::Modifiers::default(),
+            selection_anchor: None,
+            rubber_band_base: None,
+            right_splitter: None,
+            left_splitter: Some(400),
+            current_filter: choose_option.current_filter().cloned().unwrap_or_default(),
+            choose_option,
+            filters: combo_box::State::new(filters),
+        };
+        let volumes = chooser.places.refresh_volumes();
         (
-            Self {
-                dir: DirUnit::enter(std::env::current_dir().unwrap().as_path()),
-                display_name: choose_option.accept_label().to_string(),
-                showhide: false,
-                preview_big_image: false,
-                selected_paths: Vec::new(),
-                current_selected: None,
-                right_splitter: None,
-                left_splitter: Some(400),
-                current_filter: choose_option.current_filter().cloned().unwrap_or_default(),
-                choose_option,
-                filters: combo_box::State::new(filters),
-            },
-            Command::perform(update_dir_infos("."), Message::RequestNextDirs),
+            chooser,
+            Command::batch([
+                Command::perform(update_dir_infos("."), Message::RequestNextDirs),
+                volumes,
+                Command::perform(icon_theme::portal_icon_theme(), Message::IconThemeDetected),
+            ]),
         )
     }
 
+    fn scale_factor(&self) -> f64 {
+        self.scale_factor
+    }
+
     fn namespace(&self) -> String {
         String::from("Iced Filechooser")
     }
@@ -96,22 +244,283 @@ impl Application for FileChooser {
     fn update(&mut self, message: Message) -> Command<Message> {
         match message {
             Message::RequestNextDirs((dirs, pathbuf)) => {
-                if is_samedir(self.dir.current_dir(), &pathbuf) {
+                if !self.dir.is_recent() && is_samedir(self.dir.current_dir(), &pathbuf) {
                     self.dir.append_infos(dirs);
                     self.dir.set_end();
+                    return Command::batch([
+                        self.restore_scroll(),
+                        self.request_thumbnails(),
+                        self.request_previews(),
+                    ]);
                 }
                 Command::none()
             }
             Message::RequestEnter(path) => {
-                self.dir = DirUnit::enter(&path.clone());
-                Command::perform(update_dir_infos(path), Message::RequestNextDirs)
+                self.visit(Location::Dir(path.clone()));
+                self.open_location(Location::Dir(path))
+            }
+            Message::RequestRecent => {
+                self.visit(Location::Recent);
+                self.open_location(Location::Recent)
+            }
+            Message::RecentFilesLoaded(infos) => {
+                if self.dir.is_recent() {
+                    self.dir.set_recent_infos(infos);
+                    return Command::batch([
+                        self.restore_scroll(),
+                        self.request_thumbnails(),
+                        self.request_previews(),
+                    ]);
+                }
+                Command::none()
+            }
+            Message::ThumbnailLoaded((path, thumbnail)) => {
+                self.dir.set_thumbnail(path, thumbnail);
+                self.request_previews()
+            }
+            Message::PreviewLoaded((key, failed)) => {
+                self.dir.set_preview_loaded(key, failed);
+                Command::none()
+            }
+            Message::ImageInfoLoaded((path, image_info)) => {
+                self.dir.set_image_info(path, image_info);
+                Command::none()
+            }
+            Message::TextPreviewLoaded((path, preview)) => {
+                self.dir.set_text_preview(path, preview);
+                Command::none()
+            }
+            Message::RequestHexPage((path, page)) => {
+                self.dir.set_hex_page(path, page);
+                Command::none()
+            }
+            Message::RequestGoBack => {
+                self.history
+                    .save(self.scroll_offset, self.current_selected.clone());
+                let Some(visit) = self.history.back() else {
+                    return Command::none();
+                };
+                self.return_to(visit)
+            }
+            Message::RequestGoForward => {
+                self.history
+                    .save(self.scroll_offset, self.current_selected.clone());
+                let Some(visit) = self.history.forward() else {
+                    return Command::none();
+                };
+                self.return_to(visit)
+            }
+            Message::RequestGridScrolled(offset) => {
+                self.scroll_offset = offset;
+                Command::none()
+            }
+            Message::RequestMoveFocus(movement) => Command::widget(text_input_focused())
+                .map(move |editing| Message::MoveFocus((movement, editing))),
+            // the arrow keys belong to the text being edited then
+            Message::MoveFocus((_, true)) => Command::none(),
+            Message::MoveFocus((movement, false)) => {
+                match self
+                    .dir
+                    .move_focus(movement, self.showhide, &self.current_filter)
+                {
+                    Some(offset) => scrollable::snap_to(GRID_SCROLL_ID.clone(), offset),
+                    None => Command::none(),
+                }
+            }
+            Message::RequestTypeAhead(typed) => {
+                match self
+                    .dir
+                    .type_ahead(&typed, self.showhide, &self.current_filter)
+                {
+                    Some(offset) => scrollable::snap_to(GRID_SCROLL_ID.clone(), offset),
+                    None => Command::none(),
+                }
+            }
+            Message::RequestActivateFocused => {
+                let Some(info) = self
+                    .dir
+                    .focused_info(self.showhide, &self.current_filter)
+                    .cloned()
+                else {
+                    return self.update(Message::Confirm);
+                };
+                if info.is_dir() && info.is_readable() {
+                    return self.update(Message::RequestEnter(info.path()));
+                }
+                if !info.is_selectable(self.is_directory()) {
+                    return Command::none();
+                }
+                let select = if self.selected_paths.contains(&info.path()) {
+                    Command::none()
+                } else {
+                    self.update(Message::RequestMultiSelect((true, info.path())))
+                };
+                Command::batch([select, self.update(Message::Confirm)])
+            }
+            Message::RequestToggleFocused => {
+                let Some(path) = self
+                    .dir
+                    .focused_info(self.showhide, &self.current_filter)
+                    .filter(|info| info.is_selectable(self.is_directory()))
+                    .map(|info| info.path())
+                else {
+                    return Command::none();
+                };
+                let checked = !self.selected_paths.contains(&path);
+                self.update(Message::RequestMultiSelect((checked, path)))
+            }
+            Message::RequestSelectAll => {
+                if !self.is_multi_filechooser() {
+                    return Command::none();
+                }
+                for path in self.selectable_paths(|_| true) {
+                    if !self.selected_paths.contains(&path) {
+                        self.selected_paths.push(path);
+                    }
+                }
+                Command::none()
+            }
+            Message::SelectPatternChanged(pattern) => {
+                self.select_pattern = pattern;
+                Command::none()
+            }
+            Message::RequestSelectMatching => {
+                let Ok(pattern) = glob::Pattern::new(self.select_pattern.trim()) else {
+                    return Command::none();
+                };
+                if !self.is_multi_filechooser() {
+                    return Command::none();
+                }
+                for path in self.selectable_paths(|info| info.matches_pattern(&pattern)) {
+                    if !self.selected_paths.contains(&path) {
+                        self.selected_paths.push(path);
+                    }
+                }
+                Command::none()
+            }
+            Message::RequestInvertSelection => {
+                if !self.is_multi_filechooser() {
+                    return Command::none();
+                }
+                // entries which are not shown keep their selection
+                for path in self.selectable_paths(|_| true) {
+                    match self.selected_paths.iter().position(|p| *p == path) {
+                        Some(index) => {
+                            self.selected_paths.remove(index);
+                        }
+                        None => self.selected_paths.push(path),
+                    }
+                }
+                Command::none()
+            }
+            Message::RequestClearSelection => {
+                self.selected_paths.clear();
+                self.current_selected = None;
+                Command::none()
+            }
+            Message::RequestGoUp => match self.dir.get_parent_path() {
+                Some(parent) => self.update(Message::RequestEnter(parent)),
+                None => Command::none(),
+            },
+            Message::RequestToggleShowHide => self.update(Message::RequestShowHide(!self.showhide)),
+            Message::RequestEditLocation(editing) => {
+                if !editing {
+                    self.dir.set_location_input(None);
+                    return Command::none();
+                }
+                self.dir.edit_location();
+                Command::batch([
+                    text_input::focus(LOCATION_INPUT_ID.clone()),
+                    text_input::move_cursor_to_end(LOCATION_INPUT_ID.clone()),
+                ])
+            }
+            Message::RequestCompleteLocation => {
+                let Some(completed) = self
+                    .dir
+                    .location_input()
+                    .and_then(|input| complete_location(input, self.dir.current_dir()))
+                else {
+                    return Command::none();
+                };
+                self.dir.set_location_input(Some(completed));
+                text_input::move_cursor_to_end(LOCATION_INPUT_ID.clone())
+            }
+            Message::RequestPasteLocation => {
+                clipboard::read(|text| Message::LocationPasted(text.unwrap_or_default()))
+            }
+            Message::LocationInputChanged(input) => {
+                self.dir.set_location_input(Some(input));
+                Command::none()
+            }
+            Message::LocationPasted(text) => {
+                // the location bar may have been closed since
+                if self.dir.location_input().is_none() {
+                    return Command::none();
+                }
+                // a pasted path is opened right away, anything else is
+                // just typed
+                if let Some(command) =
+                    expand_location(&text, self.dir.current_dir()).and_then(|path| self.go_to(path))
+                {
+                    return command;
+                }
+                self.dir.set_location_input(Some(text));
+                Command::none()
+            }
+            Message::LocationSubmitted => {
+                let Some(input) = self.dir.location_input() else {
+                    return Command::none();
+                };
+                match expand_location(input, self.dir.current_dir())
+                    .and_then(|path| self.go_to(path))
+                {
+                    Some(command) => command,
+                    None => {
+                        self.dir.set_location_error();
+                        Command::none()
+                    }
+                }
             }
             Message::RequestShowHide(showhide) => {
                 self.showhide = showhide;
-                Command::none()
+                self.dir.restart_search();
+                self.request_shown()
             }
             Message::RequestShowImage(showimage) => {
                 self.preview_big_image = showimage;
+                self.request_shown()
+            }
+            Message::RequestShowSidebar(show_sidebar) => {
+                self.show_sidebar = show_sidebar;
+                Command::none()
+            }
+            Message::RequestAddBookmark(path) => {
+                self.places.add_bookmark(&path);
+                Command::none()
+            }
+            Message::RequestRemoveBookmark(index) => {
+                self.places.remove_bookmark(index);
+                Command::none()
+            }
+            Message::RequestRenameBookmark((index, label)) => {
+                self.places.rename_bookmark(index, &label);
+                Command::none()
+            }
+            Message::RequestMoveBookmark((from, to)) => {
+                self.places.move_bookmark(from, to);
+                Command::none()
+            }
+            Message::RequestEditBookmarks(editing) => {
+                self.places.set_editing_bookmarks(editing);
+                Command::none()
+            }
+            Message::RequestRefreshVolumes => self.places.refresh_volumes(),
+            Message::IconThemeDetected(theme) => {
+                icon_theme::set_portal_icon_theme(theme);
+                Command::none()
+            }
+            Message::VolumesLoaded(volumes) => {
+                self.places.set_volumes(volumes);
                 Command::none()
             }
             Message::RequestMultiSelect((checked, file_path)) => {
@@ -145,17 +554,115 @@ impl Application for FileChooser {
                     self.selected_paths.clear();
                 }
                 if self.selected_paths.contains(&file_path) {
-                    return Command::none();
+                    return self.request_previews();
                 }
                 self.selected_paths.push(file_path.clone());
+                self.request_previews()
+            }
+            Message::RequestItemClicked(path) => {
+                let Some(info) = self
+                    .dir
+                    .shown_infos(self.showhide, &self.current_filter)
+                    .into_iter()
+                    .find(|info| info.path() == path)
+                    .cloned()
+                else {
+                    return Command::none();
+                };
+                let selectable = info.is_selectable(self.is_directory());
+                if self.is_multi_filechooser() && selectable {
+                    if let Some(anchor) = self
+                        .selection_anchor
+                        .clone()
+                        .filter(|_| self.modifiers.shift())
+                    {
+                        self.select_range(&anchor, &path);
+                        return Command::none();
+                    }
+                    if self.modifiers.command() {
+                        self.selection_anchor = Some(path.clone());
+                        let checked = !self.selected_paths.contains(&path);
+                        return self.update(Message::RequestMultiSelect((checked, path)));
+                    }
+                }
+                self.selection_anchor = Some(path.clone());
+                if info.is_dir() && info.is_readable() {
+                    return self.update(Message::RequestEnter(path));
+                }
+                if selectable {
+                    return self.update(Message::RequestSelect(path));
+                }
+                Command::none()
+            }
+            Message::RequestRubberBandSelect(paths) => {
+                if !self.is_multi_filechooser() {
+                    return Command::none();
+                }
+                let base = self
+                    .rubber_band_base
+                    .get_or_insert_with(|| {
+                        // Ctrl adds to the selection instead of replacing it
+                        if self.modifiers.command() {
+                            self.selected_paths.clone()
+                        } else {
+                            Vec::new()
+                        }
+                    })
+                    .clone();
+                let select_dir = self.is_directory();
+                let dragged: Vec<PathBuf> = self
+                    .dir
+                    .shown_infos(self.showhide, &self.current_filter)
+                    .into_iter()
+                    .filter(|info| info.is_selectable(select_dir) && paths.contains(&info.path()))
+                    .map(|info| info.path())
+                    .collect();
+                self.selected_paths = base;
+                for path in dragged {
+                    if !self.selected_paths.contains(&path) {
+                        self.selected_paths.push(path);
+                    }
+                }
+                Command::none()
+            }
+            Message::RubberBandFinished => {
+                self.rubber_band_base = None;
+                Command::none()
+            }
+            Message::ModifiersChanged(modifiers) => {
+                self.modifiers = modifiers;
                 Command::none()
             }
             Message::SearchPatternCachedChanged(pattern) => {
                 self.dir.set_cache_pattern(&pattern);
-                Command::none()
+                self.request_shown()
             }
             Message::SearchPatternChanged => {
                 self.dir.set_pattern();
+                self.request_shown()
+            }
+            Message::RequestSearchScope(search_scope) => {
+                self.dir.set_search_scope(search_scope);
+                self.request_shown()
+            }
+            Message::RequestSearchContents(search_contents) => {
+                self.dir.set_search_contents(search_contents);
+                self.request_shown()
+            }
+            Message::RequestFuzzySearch(fuzzy) => {
+                self.dir.set_fuzzy(fuzzy);
+                self.request_shown()
+            }
+            Message::RequestCancelSearch => {
+                self.dir.cancel_search();
+                Command::none()
+            }
+            Message::SearchResultsFound((id, results)) => {
+                self.dir.append_search_results(id, results);
+                self.request_shown()
+            }
+            Message::SearchFinished(id) => {
+                self.dir.set_search_finished(id);
                 Command::none()
             }
             Message::RequestAdjustRightSplitter(right_size) => {
@@ -166,22 +673,318 @@ impl Application for FileChooser {
                 self.left_splitter = Some(left_size);
                 Command::none()
             }
-            Message::Cancel | Message::Confirm => {
+            Message::Confirm => {
+                let _ = recent::add_recent(&self.selected_paths);
                 Command::single(Action::Window(WindowAction::Close(Id::MAIN)))
             }
+            Message::Cancel => Command::single(Action::Window(WindowAction::Close(Id::MAIN))),
             Message::FilterChanged(filter) => {
                 self.current_filter = filter;
-                Command::none()
+                self.dir.restart_search();
+                self.request_shown()
             }
         }
     }
 
-    fn view(&self) -> Element<Message> {
+    fn view(&self) -> Element<'_, Message> {
         self.main_view()
     }
+
+    fn subscription(&self) -> Subscription<Message> {
+        let search = self.dir.subscription(self.showhide, &self.current_filter);
+        let mut keys = event::listen_with(shortcut_keys);
+        if self.dir.location_input().is_some() {
+            keys = Subscription::batch([keys, event::listen_with(location_keys)]);
+        }
+        if !self.show_sidebar {
+            return Subscription::batch([search, keys]);
+        }
+        // mountinfo can only be polled, it does not support inotify
+        let volumes =
+            iced::time::every(VOLUME_POLL_INTERVAL).map(|_| Message::RequestRefreshVolumes);
+        Subscription::batch([search, keys, volumes])
+    }
+}
+
+/// Whether a text input has the keyboard focus. They leave Up and Down to
+/// the window, see [`Message::MoveFocus`].
+fn text_input_focused() -> impl Operation<bool> {
+    struct TextInputFocused(bool);
+
+    impl Operation<bool> for TextInputFocused {
+        fn focusable(&mut self, state: &mut dyn Focusable, _id: Option<&widget::Id>) {
+            self.0 |= state.is_focused();
+        }
+
+        fn container(
+            &mut self,
+            _id: Option<&widget::Id>,
+            _bounds: iced::Rectangle,
+            operate_on_children: &mut dyn FnMut(&mut dyn Operation<bool>),
+        ) {
+            operate_on_children(self);
+        }
+
+        fn finish(&self) -> Outcome<bool> {
+            Outcome::Some(self.0)
+        }
+    }
+
+    TextInputFocused(false)
+}
+
+/// The window wide shortcuts, unless a widget like the search bar used
+/// the key.
+fn shortcut_keys(event: Event, status: event::Status) -> Option<Message> {
+    use keyboard::key::Named;
+    use keyboard::Key;
+    // Shift and Ctrl change what clicks do, wherever they are pressed
+    if let Event::Keyboard(keyboard::Event::ModifiersChanged(modifiers)) = event {
+        return Some(Message::ModifiersChanged(modifiers));
+    }
+    if status == event::Status::Captured {
+        return None;
+    }
+    let Event::Keyboard(keyboard::Event::KeyPressed {
+        key,
+        modifiers,
+        text,
+        ..
+    }) = event
+    else {
+        return None;
+    };
+    match key.as_ref() {
+        Key::Named(Named::ArrowLeft) if modifiers.alt() => Some(Message::RequestGoBack),
+        Key::Named(Named::ArrowRight) if modifiers.alt() => Some(Message::RequestGoForward),
+        Key::Named(Named::ArrowUp) if modifiers.alt() => Some(Message::RequestGoUp),
+        Key::Named(Named::Backspace) => Some(Message::RequestGoUp),
+        Key::Named(Named::ArrowLeft) => Some(Message::RequestMoveFocus(FocusMove::Left)),
+        Key::Named(Named::ArrowRight) => Some(Message::RequestMoveFocus(FocusMove::Right)),
+        Key::Named(Named::ArrowUp) => Some(Message::RequestMoveFocus(FocusMove::Up)),
+        Key::Named(Named::ArrowDown) => Some(Message::RequestMoveFocus(FocusMove::Down)),
+        Key::Named(Named::Home) => Some(Message::RequestMoveFocus(FocusMove::First)),
+        Key::Named(Named::End) => Some(Message::RequestMoveFocus(FocusMove::Last)),
+        Key::Named(Named::Enter) => Some(Message::RequestActivateFocused),
+        Key::Named(Named::Space) => Some(Message::RequestToggleFocused),
+        Key::Named(Named::Escape) => Some(Message::Cancel),
+        Key::Character("a") if modifiers.command() => Some(Message::RequestSelectAll),
+        Key::Character("h") if modifiers.command() => Some(Message::RequestToggleShowHide),
+        Key::Character(_) if !modifiers.command() && !modifiers.alt() => {
+            text.map(|text| Message::RequestTypeAhead(text.to_string()))
+        }
+        Key::Character("l") if modifiers.command() => Some(Message::RequestEditLocation(true)),
+        _ => None,
+    }
+}
+
+/// The shortcuts of the location bar, only listened to while it is open.
+fn location_keys(event: Event, status: event::Status) -> Option<Message> {
+    use keyboard::key::Named;
+    use keyboard::Key;
+    if status == event::Status::Captured {
+        return None;
+    }
+    let Event::Keyboard(keyboard::Event::KeyPressed { key, modifiers, .. }) = event else {
+        return None;
+    };
+    match key.as_ref() {
+        Key::Named(Named::Tab) if modifiers.is_empty() => Some(Message::RequestCompleteLocation),
+        Key::Character("v") if modifiers.command() => Some(Message::RequestPasteLocation),
+        _ => None,
+    }
 }
 
 impl FileChooser {
+    /// Leaves the current location for a new one in the history.
+    fn visit(&mut self, location: Location) {
+        self.history
+            .save(self.scroll_offset, self.current_selected.clone());
+        self.history.visit(location);
+    }
+
+    /// Opens the directory at `path`, or the one containing the file at
+    /// `path` with the file selected.
+    fn go_to(&mut self, path: PathBuf) -> Option<Command<Message>> {
+        if path.is_dir() {
+            self.visit(Location::Dir(path.clone()));
+            return Some(self.open_location(Location::Dir(path)));
+        }
+        if !path.is_file() {
+            return None;
+        }
+        let parent = path.parent()?.to_path_buf();
+        self.visit(Location::Dir(parent.clone()));
+        let command = self.open_location(Location::Dir(parent));
+        self.current_selected = Some(path.clone());
+        // shown either way, but only chosen if the mode allows, like a click
+        let name = path
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_default();
+        let selectable = std::fs::symlink_metadata(&path)
+            .ok()
+            .and_then(|metadata| read_fs_info(path.clone(), name, &metadata))
+            .is_some_and(|info| info.is_selectable(self.is_directory()));
+        if !selectable {
+            return Some(command);
+        }
+        if !self.is_multi_filechooser() {
+            self.selected_paths.clear();
+        }
+        if !self.selected_paths.contains(&path) {
+            self.selected_paths.push(path);
+        }
+        Some(command)
+    }
+
+    /// The shown entries which can be selected in this mode and pass `filter`.
+    fn selectable_paths(&self, filter: impl Fn(&FsInfo) -> bool) -> Vec<PathBuf> {
+        let select_dir = self.is_directory();
+        self.dir
+            .shown_infos(self.showhide, &self.current_filter)
+            .into_iter()
+            .filter(|info| info.is_selectable(select_dir) && filter(info))
+            .map(|info| info.path())
+            .collect()
+    }
+
+    fn selection_actions(&self) -> Element<'_, Message> {
+        let multi = self.is_multi_filechooser();
+        let mut pattern = text_input(
+            "Select matching, like *.png or image/*",
+            &self.select_pattern,
+        )
+        .padding(5)
+        .size(15);
+        if multi {
+            pattern = pattern
+                .on_input(Message::SelectPatternChanged)
+                .on_submit(Message::RequestSelectMatching);
+        }
+        column![
+            pattern,
+            row![
+                button(text("Select matching"))
+                    .on_press_maybe(multi.then_some(Message::RequestSelectMatching)),
+                button(text("Invert"))
+                    .on_press_maybe(multi.then_some(Message::RequestInvertSelection)),
+                button(text("Clear")).on_press(Message::RequestClearSelection),
+            ]
+            .spacing(5)
+        ]
+        .spacing(5)
+        .padding(5)
+        .into()
+    }
+
+    /// Adds the selectable entries from `anchor` to `path`, in display order.
+    fn select_range(&mut self, anchor: &Path, path: &Path) {
+        let select_dir = self.is_directory();
+        let infos = self.dir.shown_infos(self.showhide, &self.current_filter);
+        let (Some(from), Some(to)) = (
+            infos.iter().position(|info| info.path() == anchor),
+            infos.iter().position(|info| info.path() == path),
+        ) else {
+            return;
+        };
+        for info in &infos[from.min(to)..=from.max(to)] {
+            let path = info.path();
+            if info.is_selectable(select_dir) && !self.selected_paths.contains(&path) {
+                self.selected_paths.push(path);
+            }
+        }
+    }
+
+    fn open_location(&mut self, location: Location) -> Command<Message> {
+        self.scroll_offset = AbsoluteOffset::default();
+        self.pending_scroll = None;
+        self.selection_anchor = None;
+        previews::cancel_previews();
+        match location {
+            Location::Dir(path) => {
+                self.dir = DirUnit::enter(&path);
+                Command::perform(update_dir_infos(path), Message::RequestNextDirs)
+            }
+            Location::Recent => {
+                self.dir = DirUnit::recent();
+                Command::perform(recent::recent_infos(), Message::RecentFilesLoaded)
+            }
+        }
+    }
+
+    /// Opens a location from the history as it was left.
+    fn return_to(&mut self, visit: Visit) -> Command<Message> {
+        let command = self.open_location(visit.location);
+        self.current_selected = visit.selected;
+        self.pending_scroll = Some(visit.scroll);
+        command
+    }
+
+    /// The thumbnails and previews of the entries shown in the grid, which
+    /// change with the search, the filter and the hidden files.
+    fn request_shown(&mut self) -> Command<Message> {
+        Command::batch([self.request_thumbnails(), self.request_previews()])
+    }
+
+    /// Thumbnails are only needed while images are previewed.
+    fn request_thumbnails(&mut self) -> Command<Message> {
+        if !self.preview_big_image {
+            return Command::none();
+        }
+        let requests = self
+            .dir
+            .request_thumbnails(self.showhide, &self.current_filter);
+        Command::batch(requests.into_iter().map(|(path, mime_types)| {
+            Command::perform(
+                thumbnails::thumbnail(path, mime_types, ThumbnailSize::Normal),
+                Message::ThumbnailLoaded,
+            )
+        }))
+    }
+
+    /// The previews of the grid, and the preview, the image headers or the
+    /// start of the text or a hex dump of the selected file for the pane
+    /// beside it.
+    fn request_previews(&mut self) -> Command<Message> {
+        let selected = self.current_selected.as_ref();
+        let keys = self.dir.request_previews(
+            self.preview_big_image,
+            selected,
+            self.showhide,
+            &self.current_filter,
+        );
+        let image_info = self
+            .dir
+            .request_image_info(selected)
+            .map(|path| Command::perform(read_image_info(path), Message::ImageInfoLoaded));
+        let dark = self.theme().extended_palette().is_dark;
+        let text_preview = self
+            .dir
+            .request_text_preview(selected)
+            .map(|(path, mime_types)| {
+                Command::perform(
+                    read_text_preview(path, mime_types, dark),
+                    Message::TextPreviewLoaded,
+                )
+            });
+        Command::batch(
+            keys.into_iter()
+                .map(|key| Command::perform(previews::load_preview(key), Message::PreviewLoaded))
+                .chain(image_info)
+                .chain(text_preview),
+        )
+    }
+
+    /// Scrolls back to where the restored location was left, once its
+    /// entries are loaded.
+    fn restore_scroll(&mut self) -> Command<Message> {
+        match self.pending_scroll.take() {
+            Some(offset) => scrollable::scroll_to(GRID_SCROLL_ID.clone(), offset),
+            None => Command::none(),
+        }
+    }
+
     fn is_directory(&self) -> bool {
         self.choose_option.is_directory()
     }
@@ -189,7 +992,7 @@ impl FileChooser {
         self.choose_option.is_multi_filechooser()
     }
 
-    fn filter_box(&self) -> Element<Message> {
+    fn filter_box(&self) -> Element<'_, Message> {
         combo_box(
             &self.filters,
             "set filter",
@@ -199,7 +1002,7 @@ impl FileChooser {
         .into()
     }
 
-    fn left_view(&self) -> Element<Message> {
+    fn left_view(&self) -> Element<'_, Message> {
         let mut column = Column::new().spacing(2.);
         column = column.push(Space::with_height(10.));
         column = column.push(
@@ -229,21 +1032,32 @@ impl FileChooser {
             scrollable(row![Space::with_width(10.), column, Space::with_width(10.)])
                 .height(Length::Fill)
                 .height(Length::Fill),
+            self.selection_actions(),
             self.filter_box()
         ]
         .into()
     }
-    fn main_view(&self) -> Element<Message> {
+    fn main_view(&self) -> Element<'_, Message> {
+        let theme = self.theme();
         Split::new(
             self.left_view(),
             self.dir.view(
+                self.show_sidebar.then(|| {
+                    self.places.view(
+                        (!self.dir.is_recent()).then(|| self.dir.current_dir().as_path()),
+                        &theme,
+                    )
+                }),
                 self.showhide,
                 self.preview_big_image,
                 self.right_splitter.as_ref(),
                 self.current_selected.as_ref(),
                 self.is_directory(),
+                self.is_multi_filechooser(),
                 &self.selected_paths,
                 &self.current_filter,
+                &self.history,
+                &theme,
             ),
             self.left_splitter,
             split::Axis::Vertical,
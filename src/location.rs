@@ -0,0 +1,182 @@
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use users::os::unix::UserExt;
+
+use crate::bookmarks::uri_to_path;
+use crate::utils::home_dir;
+
+/// Replaces `$VAR` and `${VAR}`, `None` if a variable is not set.
+fn expand_vars(input: &str) -> Option<String> {
+    let mut out = String::with_capacity(input.len());
+    let mut rest = input;
+    while let Some(start) = rest.find('$') {
+        out.push_str(&rest[..start]);
+        rest = &rest[start + 1..];
+        let (name, after) = match rest.strip_prefix('{') {
+            Some(braced) => {
+                let end = braced.find('}')?;
+                (&braced[..end], &braced[end + 1..])
+            }
+            None => {
+                let end = rest
+                    .find(|c: char| !c.is_ascii_alphanumeric() && c != '_')
+                    .unwrap_or(rest.len());
+                (&rest[..end], &rest[end..])
+            }
+        };
+        if name.is_empty() {
+            out.push('$');
+        } else {
+            out.push_str(&env::var(name).ok()?);
+        }
+        rest = after;
+    }
+    out.push_str(rest);
+    Some(out)
+}
+
+/// Replaces a leading `~` or `~user`.
+fn expand_tilde(input: &str) -> Option<PathBuf> {
+    let Some(rest) = input.strip_prefix('~') else {
+        return Some(PathBuf::from(input));
+    };
+    let (user, rest) = rest.split_once('/').unwrap_or((rest, ""));
+    let home = if user.is_empty() {
+        home_dir()
+    } else {
+        users::get_user_by_name(user)?.home_dir().to_path_buf()
+    };
+    Some(home.join(rest))
+}
+
+/// Turns what was typed into the location bar into a path, relative paths
+/// are taken from `base`.
+pub fn expand_location(input: &str, base: &Path) -> Option<PathBuf> {
+    let input = input.trim();
+    if input.is_empty() {
+        return None;
+    }
+    if input.starts_with("file://") {
+        return uri_to_path(input);
+    }
+    let path = expand_tilde(&expand_vars(input)?)?;
+    Some(base.join(path))
+}
+
+/// Completes the last component to the longest prefix shared by the
+/// directories matching it, adding a `/` once it is unambiguous.
+pub fn complete_location(input: &str, base: &Path) -> Option<String> {
+    let (dir, partial) = match input.rfind('/') {
+        Some(split) => (&input[..=split], &input[split + 1..]),
+        None if input.starts_with('~') => {
+            return expand_tilde(input)?.is_dir().then(|| format!("{input}/"));
+        }
+        None => ("", input),
+    };
+    let dir = if dir.is_empty() {
+        base.to_path_buf()
+    } else {
+        expand_location(dir, base)?
+    };
+    let mut names: Vec<String> = fs::read_dir(dir)
+        .ok()?
+        .flatten()
+        .filter(|entry| entry.path().is_dir())
+        .filter_map(|entry| entry.file_name().into_string().ok())
+        .filter(|name| name.starts_with(partial))
+        // hidden directories only once a dot is typed
+        .filter(|name| partial.starts_with('.') || !name.starts_with('.'))
+        .collect();
+    names.sort();
+    let first = names.first()?;
+    let last = names.last()?;
+    let common: String = first
+        .chars()
+        .zip(last.chars())
+        .take_while(|(a, b)| a == b)
+        .map(|(a, _)| a)
+        .collect();
+    let mut completed = format!("{}{}", &input[..input.len() - partial.len()], common);
+    if names.len() == 1 {
+        completed.push('/');
+    }
+    Some(completed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn expands_locations() {
+        let base = Path::new("/base/dir");
+        let home = home_dir();
+        assert_eq!(expand_location("~", base), Some(home.join("")));
+        assert_eq!(
+            expand_location(" ~/Documents ", base),
+            Some(home.join("Documents"))
+        );
+        assert_eq!(
+            expand_location("sub/file.txt", base),
+            Some(PathBuf::from("/base/dir/sub/file.txt"))
+        );
+        assert_eq!(expand_location("/etc", base), Some(PathBuf::from("/etc")));
+        assert_eq!(
+            expand_location("file:///tmp/My%20Files", base),
+            Some(PathBuf::from("/tmp/My Files"))
+        );
+        assert_eq!(expand_location("  ", base), None);
+        assert_eq!(expand_location("$ICED_FILECHOOSER_UNSET/x", base), None);
+        assert_eq!(expand_location("~nosuchuser-xyz/x", base), None);
+    }
+
+    #[test]
+    fn expands_variables() {
+        let home = env::var("HOME").unwrap_or_default();
+        assert_eq!(
+            expand_vars("$HOME/a").as_deref(),
+            Some(&*format!("{home}/a"))
+        );
+        assert_eq!(
+            expand_vars("${HOME}b").as_deref(),
+            Some(&*format!("{home}b"))
+        );
+        assert_eq!(expand_vars("cost $ 5").as_deref(), Some("cost $ 5"));
+        assert_eq!(expand_vars("${HOME"), None);
+    }
+
+    #[test]
+    fn completes_locations() {
+        let dir = tempfile::tempdir().unwrap();
+        let base = dir.path();
+        for name in ["alpha", "alpine", "beta", ".hidden"] {
+            fs::create_dir(base.join(name)).unwrap();
+        }
+        fs::write(base.join("alps.txt"), "").unwrap();
+
+        // the prefix shared by several matches, files are not completed
+        assert_eq!(complete_location("al", base).as_deref(), Some("alp"));
+        assert_eq!(complete_location("alph", base).as_deref(), Some("alpha/"));
+        assert_eq!(complete_location("b", base).as_deref(), Some("beta/"));
+        assert_eq!(complete_location(".h", base).as_deref(), Some(".hidden/"));
+        assert_eq!(complete_location("", base).as_deref(), Some(""));
+        assert_eq!(complete_location("x", base), None);
+        assert_eq!(complete_location("nowhere/x", base), None);
+
+        let absolute = format!("{}/alpi", base.display());
+        assert_eq!(
+            complete_location(&absolute, Path::new("/")),
+            Some(format!("{absolute}ne/"))
+        );
+        let uri = format!("file://{}/be", base.display());
+        assert_eq!(
+            complete_location(&uri, Path::new("/")),
+            Some(format!("{uri}ta/"))
+        );
+        if home_dir().is_dir() {
+            assert_eq!(complete_location("~", base).as_deref(), Some("~/"));
+        }
+    }
+}
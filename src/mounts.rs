@@ -0,0 +1,307 @@
+use std::collections::HashMap;
+use std::ffi::CString;
+use std::fs;
+use std::os::unix::ffi::OsStrExt;
+use std::path::{Path, PathBuf};
+
+const MOUNTINFO: &str = "/proc/self/mountinfo";
+
+const BY_LABEL: &str = "/dev/disk/by-label";
+
+/// Kernel and system filesystems, which are no places a user picks files from.
+const PSEUDO_FS: &[&str] = &[
+    "autofs",
+    "binfmt_misc",
+    "bpf",
+    "cgroup",
+    "cgroup2",
+    "configfs",
+    "debugfs",
+    "devpts",
+    "devtmpfs",
+    "efivarfs",
+    "fuse.gvfsd-fuse",
+    "fuse.portal",
+    "fusectl",
+    "hugetlbfs",
+    "mqueue",
+    "nsfs",
+    "overlay",
+    "proc",
+    "pstore",
+    "ramfs",
+    "rpc_pipefs",
+    "securityfs",
+    "selinuxfs",
+    "squashfs",
+    "sysfs",
+    "tmpfs",
+    "tracefs",
+];
+
+/// Mount points of the system itself, `/` is already listed as "File System".
+const SYSTEM_DIRS: &[&str] = &[
+    "/boot", "/dev", "/efi", "/proc", "/snap", "/sys", "/usr", "/var",
+];
+
+const NETWORK_FS: &[&str] = &["afs", "cifs", "fuse.sshfs", "nfs", "nfs4", "smb3", "smbfs"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VolumeKind {
+    Removable,
+    Network,
+    Fuse,
+    Fixed,
+}
+
+impl VolumeKind {
+    pub fn icon(&self) -> &'static str {
+        match self {
+            VolumeKind::Removable => "drive-removable-media",
+            VolumeKind::Network => "folder-remote",
+            VolumeKind::Fuse | VolumeKind::Fixed => "drive-harddisk",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Volume {
+    pub label: String,
+    pub mount_point: PathBuf,
+    pub kind: VolumeKind,
+    /// Free and total bytes, `None` when `statvfs` failed.
+    pub space: Option<(u64, u64)>,
+}
+
+/// Undoes the octal escapes mountinfo uses for spaces and such, like `\040`.
+fn unescape_octal(field: &str) -> Vec<u8> {
+    let bytes = field.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'\\' {
+            if let Some(value) = bytes
+                .get(i + 1..i + 4)
+                .and_then(|octal| std::str::from_utf8(octal).ok())
+                .and_then(|octal| u8::from_str_radix(octal, 8).ok())
+            {
+                out.push(value);
+                i += 4;
+                continue;
+            }
+        }
+        out.push(bytes[i]);
+        i += 1;
+    }
+    out
+}
+
+/// Undoes the `\x20` escapes udev uses in `/dev/disk/by-label`.
+fn unescape_hex(name: &str) -> String {
+    let mut out = Vec::with_capacity(name.len());
+    let bytes = name.as_bytes();
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'\\' && bytes.get(i + 1) == Some(&b'x') {
+            if let Some(value) = bytes
+                .get(i + 2..i + 4)
+                .and_then(|hex| std::str::from_utf8(hex).ok())
+                .and_then(|hex| u8::from_str_radix(hex, 16).ok())
+            {
+                out.push(value);
+                i += 4;
+                continue;
+            }
+        }
+        out.push(bytes[i]);
+        i += 1;
+    }
+    String::from_utf8_lossy(&out).to_string()
+}
+
+/// Maps the resolved device nodes to their filesystem labels.
+fn device_labels() -> HashMap<PathBuf, String> {
+    let Ok(entries) = fs::read_dir(BY_LABEL) else {
+        return HashMap::new();
+    };
+    entries
+        .flatten()
+        .filter_map(|entry| {
+            let device = fs::canonicalize(entry.path()).ok()?;
+            Some((device, unescape_hex(&entry.file_name().to_string_lossy())))
+        })
+        .collect()
+}
+
+/// Free space available to the user and the total size, in bytes.
+fn statvfs(path: &Path) -> Option<(u64, u64)> {
+    let path = CString::new(path.as_os_str().as_bytes()).ok()?;
+    let mut stat: libc::statvfs = unsafe { std::mem::zeroed() };
+    if unsafe { libc::statvfs(path.as_ptr(), &mut stat) } != 0 {
+        return None;
+    }
+    let block = stat.f_frsize as u64;
+    Some((stat.f_bavail as u64 * block, stat.f_blocks as u64 * block))
+}
+
+struct MountEntry {
+    root: Vec<u8>,
+    mount_point: PathBuf,
+    fs_type: String,
+    source: String,
+}
+
+/// A line looks like
+/// `36 35 98:0 /mnt1 /mnt2 rw,noatime master:1 - ext3 /dev/root rw,errors=continue`,
+/// with any number of optional fields before the `-`.
+fn parse_line(line: &str) -> Option<MountEntry> {
+    let (mount, fs) = line.split_once(" - ")?;
+    let mut mount = mount.split(' ');
+    let root = unescape_octal(mount.nth(3)?);
+    let mount_point = PathBuf::from(std::ffi::OsStr::from_bytes(&unescape_octal(mount.next()?)));
+    let mut fs = fs.split(' ');
+    let fs_type = fs.next()?.to_string();
+    let source = String::from_utf8_lossy(&unescape_octal(fs.next()?)).to_string();
+    Some(MountEntry {
+        root,
+        mount_point,
+        fs_type,
+        source,
+    })
+}
+
+fn is_system_dir(path: &Path) -> bool {
+    path == Path::new("/")
+        || SYSTEM_DIRS.iter().any(|dir| path.starts_with(dir))
+        || (path.starts_with("/run") && !path.starts_with("/run/media"))
+}
+
+/// Lists the mounts worth showing from the content of `/proc/self/mountinfo`.
+pub fn parse_volumes(mountinfo: &str) -> Vec<Volume> {
+    let labels = device_labels();
+    let mut volumes: Vec<Volume> = Vec::new();
+    for entry in mountinfo.lines().filter_map(parse_line) {
+        // bind mounts of a subdirectory show up again under their own root
+        if entry.root != b"/"
+            || PSEUDO_FS.contains(&entry.fs_type.as_str())
+            || is_system_dir(&entry.mount_point)
+        {
+            continue;
+        }
+        let kind = if NETWORK_FS.contains(&entry.fs_type.as_str()) {
+            VolumeKind::Network
+        } else if entry.fs_type.starts_with("fuse") {
+            VolumeKind::Fuse
+        } else if entry.mount_point.starts_with("/media")
+            || entry.mount_point.starts_with("/run/media")
+        {
+            VolumeKind::Removable
+        } else {
+            VolumeKind::Fixed
+        };
+        let label = fs::canonicalize(&entry.source)
+            .ok()
+            .and_then(|device| labels.get(&device).cloned())
+            .or_else(|| {
+                entry
+                    .mount_point
+                    .file_name()
+                    .map(|name| name.to_string_lossy().to_string())
+            })
+            .unwrap_or_else(|| entry.source.clone());
+        // a later mount on the same point hides the earlier one
+        volumes.retain(|volume| volume.mount_point != entry.mount_point);
+        volumes.push(Volume {
+            label,
+            space: statvfs(&entry.mount_point),
+            mount_point: entry.mount_point,
+            kind,
+        });
+    }
+    volumes
+}
+
+/// Reads the mount table and the free space of the volumes off the UI
+/// thread, where a dead network mount would block `statvfs` for long.
+pub async fn read_volumes() -> Option<Vec<Volume>> {
+    tokio::task::spawn_blocking(|| {
+        let mountinfo = fs::read_to_string(MOUNTINFO).ok()?;
+        Some(parse_volumes(&mountinfo))
+    })
+    .await
+    .ok()
+    .flatten()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unescapes_names() {
+        assert_eq!(unescape_octal(r"/mnt/My\040Disk"), b"/mnt/My Disk");
+        assert_eq!(unescape_octal(r"tab\011and\134"), b"tab\tand\\");
+        assert_eq!(unescape_octal(r"short\04"), br"short\04");
+        assert_eq!(unescape_octal(r"not\999"), br"not\999");
+        assert_eq!(unescape_hex(r"My\x20Disk"), "My Disk");
+        assert_eq!(unescape_hex(r"bad\xzz\x2"), r"bad\xzz\x2");
+    }
+
+    #[test]
+    fn parses_lines() {
+        let entry = parse_line(
+            r"36 35 98:0 / /run/media/me/USB\040Stick rw,noatime master:1 shared:2 - vfat /dev/sdb1 rw",
+        )
+        .unwrap();
+        assert_eq!(entry.root, b"/");
+        assert_eq!(entry.mount_point, Path::new("/run/media/me/USB Stick"));
+        assert_eq!(entry.fs_type, "vfat");
+        assert_eq!(entry.source, "/dev/sdb1");
+
+        let entry = parse_line("40 1 0:40 /home/me/shared /srv rw - ext4 /dev/sda2 rw").unwrap();
+        assert_eq!(entry.root, b"/home/me/shared");
+        assert!(parse_line("36 35 98:0 / /mnt rw").is_none());
+        assert!(parse_line("36 35 - ext4").is_none());
+    }
+
+    #[test]
+    fn filters_volumes() {
+        let mountinfo = "\
+22 1 8:2 / / rw - ext4 /dev/nonexistent2 rw
+23 22 0:21 / /proc rw - proc proc rw
+24 22 0:22 / /sys rw - sysfs sysfs rw
+25 22 8:1 / /boot/efi rw - vfat /dev/nonexistent1 rw
+26 22 0:23 / /run/user/1000 rw - tmpfs tmpfs rw
+27 22 0:24 / /run/user/1000/doc rw - fuse.portal portal rw
+28 22 8:3 / /home rw - ext4 /dev/nonexistent3 rw
+29 28 8:3 /me/shared /srv/shared rw - ext4 /dev/nonexistent3 rw
+30 22 8:17 / /run/media/me/USB\\040Stick rw - vfat /dev/nonexistent17 rw
+31 22 0:30 / /mnt/nas rw - nfs4 nas:/export rw
+32 22 0:31 / /home/me/remote rw - fuse.sshfs me@host:/ rw
+33 22 0:32 / /mnt/mtp rw - fuse.jmtpfs jmtpfs rw
+34 22 8:4 / /mnt/mtp rw - ext4 /dev/nonexistent4 rw
+";
+        let volumes: Vec<(String, PathBuf, VolumeKind)> = parse_volumes(mountinfo)
+            .into_iter()
+            .map(|volume| (volume.label, volume.mount_point, volume.kind))
+            .collect();
+        assert_eq!(
+            volumes,
+            [
+                ("home".into(), "/home".into(), VolumeKind::Fixed),
+                (
+                    "USB Stick".into(),
+                    "/run/media/me/USB Stick".into(),
+                    VolumeKind::Removable
+                ),
+                ("nas".into(), "/mnt/nas".into(), VolumeKind::Network),
+                (
+                    "remote".into(),
+                    "/home/me/remote".into(),
+                    VolumeKind::Network
+                ),
+                ("mtp".into(), "/mnt/mtp".into(), VolumeKind::Fixed),
+            ]
+        );
+    }
+}
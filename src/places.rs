@@ -0,0 +1,365 @@
+use iced::widget::{
+    button, column, row, scrollable, text, text_input, Button, Column, Space, Text,
+};
+use iced::{theme, Command, Element, Length, Theme};
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::bookmarks::Bookmarks;
+use crate::dirfs::format_size;
+use crate::icon_cache::{bundled_icon, get_icon_handle, IconHandle, IconKey};
+use crate::icon_theme::icon_theme;
+use crate::mounts::{read_volumes, Volume};
+use crate::utils::{home_dir, xdg_config_home, xdg_data_home};
+use crate::Message;
+
+const SIDEBAR_WIDTH: f32 = 180.;
+
+/// The `user-dirs.dirs` keys, in the order they are listed, with their icons.
+const USER_DIRS: &[(&str, &str)] = &[
+    ("XDG_DESKTOP_DIR", "user-desktop"),
+    ("XDG_DOCUMENTS_DIR", "folder-documents"),
+    ("XDG_DOWNLOAD_DIR", "folder-download"),
+    ("XDG_MUSIC_DIR", "folder-music"),
+    ("XDG_PICTURES_DIR", "folder-pictures"),
+    ("XDG_VIDEOS_DIR", "folder-videos"),
+    ("XDG_TEMPLATES_DIR", "folder-templates"),
+    ("XDG_PUBLICSHARE_DIR", "folder-publicshare"),
+];
+
+/// Looked up when drawn, the icon theme may change once the portal answers.
+fn themed_icon(icon: &str) -> IconHandle {
+    get_icon_handle(IconKey::themed(&icon_theme(), icon, 16)).unwrap_or_else(|| bundled_icon(true))
+}
+
+fn section_title(title: &str) -> Text<'_> {
+    text(title).font(iced::Font {
+        weight: iced::font::Weight::Bold,
+        ..Default::default()
+    })
+}
+
+fn small_button(label: &str) -> Button<'_, Message> {
+    button(text(label).shaping(text::Shaping::Advanced).size(14))
+        .style(theme::Button::Text)
+        .padding(2)
+}
+
+#[derive(Debug, Clone)]
+pub struct Place {
+    label: String,
+    path: PathBuf,
+    icon: &'static str,
+}
+
+impl Place {
+    fn new(label: &str, path: PathBuf, icon: &'static str) -> Self {
+        Self {
+            label: label.to_string(),
+            path,
+            icon,
+        }
+    }
+
+    fn view(&self, current_dir: &Path) -> Element<'_, Message> {
+        place_button(
+            &self.label,
+            self.icon,
+            current_dir == self.path,
+            Message::RequestEnter(self.path.clone()),
+        )
+    }
+}
+
+fn place_button<'a>(
+    label: &str,
+    icon: &str,
+    selected: bool,
+    on_press: Message,
+) -> Element<'a, Message> {
+    let style = if selected {
+        theme::Button::Primary
+    } else {
+        theme::Button::Text
+    };
+    button(
+        row![
+            themed_icon(icon).view(16, 16),
+            text(label).shaping(text::Shaping::Advanced)
+        ]
+        .spacing(8)
+        .align_items(iced::Alignment::Center),
+    )
+    .style(style)
+    .width(Length::Fill)
+    .on_press(on_press)
+    .into()
+}
+
+/// Reads `$XDG_CONFIG_HOME/user-dirs.dirs`, lines like
+/// `XDG_DOWNLOAD_DIR="$HOME/Downloads"`.
+///
+/// The directory names are localized by `xdg-user-dirs-update`, so the
+/// last component doubles as the label.
+fn user_dirs() -> Vec<Place> {
+    let Ok(content) = fs::read_to_string(xdg_config_home().join("user-dirs.dirs")) else {
+        return Vec::new();
+    };
+    let home = home_dir();
+    let mut dirs: Vec<(usize, Place)> = Vec::new();
+    for line in content.lines() {
+        let Some((key, value)) = line.trim().split_once('=') else {
+            continue;
+        };
+        let Some(order) = USER_DIRS.iter().position(|(name, _)| *name == key) else {
+            continue;
+        };
+        let value = value.trim_matches('"');
+        let path = match value.strip_prefix("$HOME") {
+            Some(rest) => home.join(rest.trim_start_matches('/')),
+            None if value.starts_with('/') => PathBuf::from(value),
+            None => continue,
+        };
+        // disabled entries point at the home directory itself
+        if path == home || !path.is_dir() {
+            continue;
+        }
+        let label = path
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_default();
+        dirs.push((order, Place::new(&label, path, USER_DIRS[order].1)));
+    }
+    dirs.sort_by_key(|(order, _)| *order);
+    dirs.into_iter().map(|(_, place)| place).collect()
+}
+
+#[derive(Debug)]
+pub struct Places {
+    places: Vec<Place>,
+    bookmarks: Bookmarks,
+    editing_bookmarks: bool,
+    /// Why the bookmarks could not be written the last time.
+    bookmarks_error: Option<String>,
+    /// Whether the volumes are being read, see [`Places::refresh_volumes`].
+    refreshing_volumes: bool,
+    volumes: Vec<Volume>,
+}
+
+impl Places {
+    pub fn load() -> Self {
+        let mut places = vec![Place::new("Home", home_dir(), "user-home")];
+        places.append(&mut user_dirs());
+        places.push(Place::new(
+            "File System",
+            PathBuf::from("/"),
+            "drive-harddisk",
+        ));
+        let trash = xdg_data_home().join("Trash/files");
+        if trash.is_dir() {
+            places.push(Place::new("Trash", trash, "user-trash"));
+        }
+        Self {
+            places,
+            bookmarks: Bookmarks::load(),
+            editing_bookmarks: false,
+            bookmarks_error: None,
+            refreshing_volumes: false,
+            volumes: Vec::new(),
+        }
+    }
+
+    /// Rereads the mounted volumes and their free space, unless a mount
+    /// still hangs the last time.
+    pub fn refresh_volumes(&mut self) -> Command<Message> {
+        if self.refreshing_volumes {
+            return Command::none();
+        }
+        self.refreshing_volumes = true;
+        Command::perform(read_volumes(), Message::VolumesLoaded)
+    }
+
+    pub fn set_volumes(&mut self, volumes: Option<Vec<Volume>>) {
+        self.refreshing_volumes = false;
+        if let Some(volumes) = volumes {
+            self.volumes = volumes;
+        }
+    }
+
+    fn volumes_view(&self, current_dir: &Path) -> Element<'_, Message> {
+        let mut volumes = Column::new().spacing(2).push(section_title("Devices"));
+        for volume in self.volumes.iter() {
+            volumes = volumes.push(place_button(
+                &volume.label,
+                volume.kind.icon(),
+                current_dir == volume.mount_point,
+                Message::RequestEnter(volume.mount_point.clone()),
+            ));
+            if let Some((free, total)) = volume.space {
+                volumes = volumes.push(
+                    text(format!(
+                        "{} free of {}",
+                        format_size(free),
+                        format_size(total)
+                    ))
+                    .size(11),
+                );
+            }
+        }
+        volumes.into()
+    }
+
+    /// Applies `edit` to the bookmarks as they are in the file now, so the
+    /// ones other programs added since startup are kept, and writes them.
+    fn edit_bookmarks(&mut self, edit: impl FnOnce(&mut Bookmarks)) {
+        self.bookmarks.reload();
+        edit(&mut self.bookmarks);
+        self.bookmarks_error = self
+            .bookmarks
+            .save()
+            .err()
+            .map(|error| format!("Bookmarks not saved: {error}"));
+    }
+
+    pub fn add_bookmark(&mut self, path: &Path) {
+        self.edit_bookmarks(|bookmarks| bookmarks.add(path));
+    }
+
+    /// `index` is into the bookmarks shown, which may have moved in the file.
+    pub fn remove_bookmark(&mut self, index: usize) {
+        let Some(bookmark) = self.bookmarks.entries().get(index).cloned() else {
+            return;
+        };
+        self.edit_bookmarks(|bookmarks| {
+            if let Some(index) = bookmarks.position(&bookmark) {
+                bookmarks.remove(index);
+            }
+        });
+    }
+
+    pub fn move_bookmark(&mut self, from: usize, to: usize) {
+        let entries = self.bookmarks.entries();
+        let (Some(moved), Some(target)) = (entries.get(from).cloned(), entries.get(to).cloned())
+        else {
+            return;
+        };
+        self.edit_bookmarks(|bookmarks| {
+            if let (Some(from), Some(to)) =
+                (bookmarks.position(&moved), bookmarks.position(&target))
+            {
+                bookmarks.move_to(from, to);
+            }
+        });
+    }
+
+    /// Renames while typing, the file is written once editing is done.
+    pub fn rename_bookmark(&mut self, index: usize, label: &str) {
+        self.bookmarks.rename(index, label);
+    }
+
+    pub fn set_editing_bookmarks(&mut self, editing: bool) {
+        if self.editing_bookmarks && !editing {
+            self.edit_bookmarks(|_| {});
+        }
+        self.editing_bookmarks = editing;
+    }
+
+    fn bookmarks_view(&self, current_dir: &Path, theme: &Theme) -> Element<'_, Message> {
+        let mut header = row![section_title("Bookmarks"), Space::with_width(Length::Fill)]
+            .spacing(2)
+            .align_items(iced::Alignment::Center);
+        if !current_dir.as_os_str().is_empty() && !self.bookmarks.contains(current_dir) {
+            header = header.push(
+                small_button("+").on_press(Message::RequestAddBookmark(current_dir.to_path_buf())),
+            );
+        }
+        if !self.bookmarks.entries().is_empty() {
+            let edit = if self.editing_bookmarks {
+                "Done"
+            } else {
+                "Edit"
+            };
+            header = header.push(
+                small_button(edit).on_press(Message::RequestEditBookmarks(!self.editing_bookmarks)),
+            );
+        }
+        let mut bookmarks = Column::new().spacing(2).push(header);
+        if let Some(error) = &self.bookmarks_error {
+            bookmarks = bookmarks.push(
+                text(error)
+                    .size(11)
+                    .style(theme::Text::Color(theme.palette().danger)),
+            );
+        }
+        let last = self.bookmarks.entries().len().saturating_sub(1);
+        for (index, bookmark) in self.bookmarks.entries().iter().enumerate() {
+            if self.editing_bookmarks {
+                let mut edit_row = row![text_input(&bookmark.label(), bookmark.custom_label())
+                    .on_input(move |label| Message::RequestRenameBookmark((index, label)))
+                    .on_submit(Message::RequestEditBookmarks(false))
+                    .size(14)
+                    .padding(2)]
+                .spacing(2)
+                .align_items(iced::Alignment::Center);
+                let mut up = small_button("▲");
+                if index > 0 {
+                    up = up.on_press(Message::RequestMoveBookmark((index, index - 1)));
+                }
+                let mut down = small_button("▼");
+                if index < last {
+                    down = down.on_press(Message::RequestMoveBookmark((index, index + 1)));
+                }
+                edit_row = edit_row
+                    .push(up)
+                    .push(down)
+                    .push(small_button("✕").on_press(Message::RequestRemoveBookmark(index)));
+                bookmarks = bookmarks.push(edit_row);
+                continue;
+            }
+            // remote locations cannot be entered
+            let Some(path) = bookmark.path() else {
+                continue;
+            };
+            bookmarks = bookmarks.push(place_button(
+                &bookmark.label(),
+                "user-bookmarks",
+                current_dir == path,
+                Message::RequestEnter(path),
+            ));
+        }
+        bookmarks.into()
+    }
+
+    /// `current_dir` is `None` while the recent files are shown.
+    pub fn view(&self, current_dir: Option<&Path>, theme: &Theme) -> Element<'_, Message> {
+        let current_dir = current_dir
+            .map(|dir| dir.canonicalize().unwrap_or(dir.to_path_buf()))
+            .unwrap_or_default();
+        let mut places = Column::new().spacing(2).push(place_button(
+            "Recent",
+            "document-open-recent",
+            current_dir.as_os_str().is_empty(),
+            Message::RequestRecent,
+        ));
+        for place in self.places.iter() {
+            places = places.push(place.view(&current_dir));
+        }
+        if !self.volumes.is_empty() {
+            places = places
+                .push(Space::with_height(10))
+                .push(self.volumes_view(&current_dir));
+        }
+        places = places
+            .push(Space::with_height(10))
+            .push(self.bookmarks_view(&current_dir, theme));
+        column![
+            section_title("Places"),
+            scrollable(places).height(Length::Fill)
+        ]
+        .spacing(5)
+        .padding(5)
+        .width(SIDEBAR_WIDTH)
+        .into()
+    }
+}
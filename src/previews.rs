@@ -0,0 +1,66 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, LazyLock, Mutex};
+
+use tokio::sync::{mpsc, oneshot};
+
+use crate::icon_cache::{get_icon_handle, IconKey};
+
+/// Requests beyond this wait for a worker, instead of piling up decoded
+/// images nobody looks at anymore.
+const PREVIEW_QUEUE_SIZE: usize = 32;
+
+/// Bumped to drop the queued requests, see [`cancel_previews`].
+static GENERATION: AtomicU64 = AtomicU64::new(0);
+
+struct Job {
+    key: IconKey,
+    generation: u64,
+    /// Sent whether the preview failed to load.
+    done: oneshot::Sender<bool>,
+}
+
+static QUEUE: LazyLock<mpsc::Sender<Job>> = LazyLock::new(|| {
+    let (sender, receiver) = mpsc::channel::<Job>(PREVIEW_QUEUE_SIZE);
+    let receiver = Arc::new(Mutex::new(receiver));
+    let workers = std::thread::available_parallelism()
+        .map(|threads| threads.get() / 2)
+        .unwrap_or(1)
+        .max(1);
+    for index in 0..workers {
+        let receiver = receiver.clone();
+        let _ = std::thread::Builder::new()
+            .name(format!("preview-{index}"))
+            .spawn(move || loop {
+                let Some(job) = receiver.lock().unwrap().blocking_recv() else {
+                    return;
+                };
+                // decoded into the icon cache, where the view finds it
+                let failed = job.generation == GENERATION.load(Ordering::Relaxed)
+                    && get_icon_handle(job.key).is_none();
+                let _ = job.done.send(failed);
+            });
+    }
+    sender
+});
+
+/// Drops the previews still queued, like when leaving their directory.
+pub fn cancel_previews() {
+    GENERATION.fetch_add(1, Ordering::Relaxed);
+}
+
+/// Decodes and downscales the preview of `key` on a worker, and returns the
+/// key once it is in the icon cache, or failed to load, or was cancelled,
+/// along with whether it failed.
+pub async fn load_preview(key: IconKey) -> (IconKey, bool) {
+    let (done, finished) = oneshot::channel();
+    let job = Job {
+        key: key.clone(),
+        generation: GENERATION.load(Ordering::Relaxed),
+        done,
+    };
+    let mut failed = false;
+    if QUEUE.send(job).await.is_ok() {
+        failed = finished.await.unwrap_or_default();
+    }
+    (key, failed)
+}
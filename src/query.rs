@@ -0,0 +1,366 @@
+use std::fmt::Display;
+use std::time::{Duration, SystemTime};
+
+use fuzzy_matcher::skim::SkimMatcherV2;
+use fuzzy_matcher::FuzzyMatcher;
+
+use std::sync::LazyLock;
+
+use crate::dirfs::FsInfo;
+
+static FUZZY_MATCHER: LazyLock<SkimMatcherV2> = LazyLock::new(SkimMatcherV2::default);
+
+/// A parsed search box query, like `type:image size:>2M modified:<7d name:*.png`.
+///
+/// Every term has to match. Terms without a known `key:` prefix match `*term*`
+/// against the file name, so a bare word behaves like the old glob search,
+/// or are matched fuzzily when ranking with [`Query::fuzzy_match`].
+#[derive(Debug, Clone, Default)]
+pub struct Query {
+    predicates: Vec<Predicate>,
+    words: Vec<glob::Pattern>,
+    text: String,
+}
+
+#[derive(Debug, Clone)]
+enum Predicate {
+    Name(glob::Pattern),
+    Ext(Vec<String>),
+    Type(String),
+    Size(Compare, u64),
+    Modified(Compare, Duration),
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Compare {
+    Less,
+    LessEqual,
+    Equal,
+    GreaterEqual,
+    Greater,
+}
+
+impl Compare {
+    fn split(value: &str, default: Compare) -> (Self, &str) {
+        for (prefix, compare) in [
+            ("<=", Compare::LessEqual),
+            (">=", Compare::GreaterEqual),
+            ("<", Compare::Less),
+            (">", Compare::Greater),
+            ("=", Compare::Equal),
+        ] {
+            if let Some(value) = value.strip_prefix(prefix) {
+                return (compare, value);
+            }
+        }
+        (default, value)
+    }
+
+    fn matches<T: PartialOrd>(&self, left: T, right: T) -> bool {
+        match self {
+            Compare::Less => left < right,
+            Compare::LessEqual => left <= right,
+            Compare::Equal => left == right,
+            Compare::GreaterEqual => left >= right,
+            Compare::Greater => left > right,
+        }
+    }
+}
+
+/// Where and why a query failed to parse.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct QueryError {
+    term: String,
+    reason: String,
+}
+
+impl Display for QueryError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}", self.term, self.reason)
+    }
+}
+
+impl QueryError {
+    fn new(term: &str, reason: impl ToString) -> Self {
+        Self {
+            term: term.to_string(),
+            reason: reason.to_string(),
+        }
+    }
+}
+
+/// Splits on whitespace, keeping `"quoted terms"` together.
+fn split_terms(input: &str) -> Result<Vec<String>, QueryError> {
+    let mut terms = Vec::new();
+    let mut current = String::new();
+    let mut quoted = false;
+    for c in input.chars() {
+        match c {
+            '"' => quoted = !quoted,
+            c if c.is_whitespace() && !quoted => {
+                if !current.is_empty() {
+                    terms.push(std::mem::take(&mut current));
+                }
+            }
+            c => current.push(c),
+        }
+    }
+    if quoted {
+        return Err(QueryError::new(input, "unclosed quote"));
+    }
+    if !current.is_empty() {
+        terms.push(current);
+    }
+    Ok(terms)
+}
+
+/// Parses sizes like `2M`, `10k`, `1.5GiB` or `300`, in powers of 1024.
+fn parse_size(value: &str) -> Option<u64> {
+    let value = value
+        .trim_end_matches(['b', 'B'])
+        .trim_end_matches(['i', 'I']);
+    let split = value
+        .find(|c: char| !c.is_ascii_digit() && c != '.')
+        .unwrap_or(value.len());
+    let (number, unit) = value.split_at(split);
+    let number: f64 = number.parse().ok()?;
+    let multiplier: u64 = match unit.to_ascii_lowercase().as_str() {
+        "" => 1,
+        "k" => 1 << 10,
+        "m" => 1 << 20,
+        "g" => 1 << 30,
+        "t" => 1 << 40,
+        _ => return None,
+    };
+    let size = number * multiplier as f64;
+    // the cast would saturate instead
+    (size <= u64::MAX as f64).then_some(size as u64)
+}
+
+/// Parses ages like `30s`, `15min`, `12h`, `7d`, `2w`, `3mo` or `1y`.
+fn parse_age(value: &str) -> Option<Duration> {
+    let split = value
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(value.len());
+    let (number, unit) = value.split_at(split);
+    let number: u64 = number.parse().ok()?;
+    let seconds: u64 = match unit {
+        "s" => 1,
+        "m" | "min" => 60,
+        "h" => 60 * 60,
+        "" | "d" => 24 * 60 * 60,
+        "w" => 7 * 24 * 60 * 60,
+        "mo" => 30 * 24 * 60 * 60,
+        "y" => 365 * 24 * 60 * 60,
+        _ => return None,
+    };
+    number.checked_mul(seconds).map(Duration::from_secs)
+}
+
+/// The keys of the `key:value` terms, a term with any other prefix, like
+/// `notes:2024`, is part of a name.
+const KEYS: &[&str] = &["name", "ext", "type", "size", "modified"];
+
+fn is_predicate(term: &str) -> bool {
+    term.split_once(':')
+        .is_some_and(|(key, _)| KEYS.contains(&key))
+}
+
+fn parse_term(term: &str) -> Result<Predicate, QueryError> {
+    let Some((key, value)) = term.split_once(':') else {
+        return Err(QueryError::new(term, "expected key:value"));
+    };
+    if value.is_empty() {
+        return Err(QueryError::new(term, "missing value"));
+    }
+    match key {
+        "name" => glob::Pattern::new(value)
+            .map(Predicate::Name)
+            .map_err(|e| QueryError::new(term, e)),
+        "ext" => Ok(Predicate::Ext(
+            value
+                .split(',')
+                .map(|ext| ext.trim_start_matches('.').to_lowercase())
+                .collect(),
+        )),
+        "type" => Ok(Predicate::Type(value.to_lowercase())),
+        "size" => {
+            let (compare, value) = Compare::split(value, Compare::Equal);
+            parse_size(value)
+                .map(|size| Predicate::Size(compare, size))
+                .ok_or_else(|| QueryError::new(term, "expected a size like >2M"))
+        }
+        "modified" => {
+            let (compare, value) = Compare::split(value, Compare::Less);
+            parse_age(value)
+                .map(|age| Predicate::Modified(compare, age))
+                .ok_or_else(|| QueryError::new(term, "expected an age like <7d"))
+        }
+        _ => Err(QueryError::new(term, "unknown key")),
+    }
+}
+
+impl Query {
+    pub fn parse(input: &str) -> Result<Self, QueryError> {
+        let mut query = Self::default();
+        for term in split_terms(input)? {
+            if is_predicate(&term) {
+                query.predicates.push(parse_term(&term)?);
+                continue;
+            }
+            query.words.push(
+                glob::Pattern::new(&format!("*{term}*")).map_err(|e| QueryError::new(&term, e))?,
+            );
+            query.text.push_str(&term);
+        }
+        Ok(query)
+    }
+
+    pub fn matches(&self, info: &FsInfo) -> bool {
+        self.words.iter().all(|word| word.matches(info.name()))
+            && self
+                .predicates
+                .iter()
+                .all(|predicate| predicate.matches(info))
+    }
+
+    /// Checks only the terms looking at the name, to rule out entries
+    /// before reading their metadata.
+    pub fn matches_name(&self, name: &str) -> bool {
+        self.words.iter().all(|word| word.matches(name))
+            && self.predicates.iter().all(|predicate| match predicate {
+                Predicate::Name(pattern) => pattern.matches(name),
+                _ => true,
+            })
+    }
+
+    /// The fuzzy score of a bare name, see [`Query::matches_name`].
+    pub fn fuzzy_score(&self, name: &str) -> Option<i64> {
+        let names_match = self.predicates.iter().all(|predicate| match predicate {
+            Predicate::Name(pattern) => pattern.matches(name),
+            _ => true,
+        });
+        if !names_match {
+            return None;
+        }
+        if self.text.is_empty() {
+            return Some(0);
+        }
+        FUZZY_MATCHER.fuzzy_match(name, &self.text)
+    }
+
+    /// Scores the name against the bare words like fzf does, returning the
+    /// score and the char indices of the matched characters.
+    pub fn fuzzy_match(&self, info: &FsInfo) -> Option<(i64, Vec<usize>)> {
+        if !self
+            .predicates
+            .iter()
+            .all(|predicate| predicate.matches(info))
+        {
+            return None;
+        }
+        if self.text.is_empty() {
+            return Some((0, Vec::new()));
+        }
+        FUZZY_MATCHER.fuzzy_indices(info.name(), &self.text)
+    }
+}
+
+impl Predicate {
+    fn matches(&self, info: &FsInfo) -> bool {
+        match self {
+            Predicate::Name(pattern) => pattern.matches(info.name()),
+            Predicate::Ext(exts) => info.path().extension().is_some_and(|ext| {
+                let ext = ext.to_string_lossy().to_lowercase();
+                exts.contains(&ext)
+            }),
+            Predicate::Type(kind) => match kind.as_str() {
+                "dir" | "directory" | "folder" => info.is_dir(),
+                "file" => info.is_file(),
+                kind => {
+                    let FsInfo::File { mimeinfo, .. } = info else {
+                        return false;
+                    };
+                    mimeinfo.iter().any(|mime| {
+                        if kind.contains('/') {
+                            glob::Pattern::new(kind).is_ok_and(|p| p.matches(mime.as_ref()))
+                        } else {
+                            mime.type_() == kind
+                        }
+                    })
+                }
+            },
+            Predicate::Size(compare, size) => info.is_file() && compare.matches(info.size(), *size),
+            // "modified:<7d" reads as "modified less than seven days ago"
+            Predicate::Modified(compare, age) => info
+                .modified()
+                .and_then(|modified| SystemTime::now().duration_since(modified).ok())
+                .is_some_and(|elapsed| compare.matches(elapsed, *age)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DAY: u64 = 24 * 60 * 60;
+
+    #[test]
+    fn parses_sizes() {
+        assert_eq!(parse_size("300"), Some(300));
+        assert_eq!(parse_size("10k"), Some(10 * 1024));
+        assert_eq!(parse_size("2M"), Some(2 * 1024 * 1024));
+        assert_eq!(parse_size("2MB"), Some(2 * 1024 * 1024));
+        assert_eq!(parse_size("1.5GiB"), Some(3 * 512 * 1024 * 1024));
+        assert_eq!(parse_size("1t"), Some(1 << 40));
+        assert_eq!(parse_size("2x"), None);
+        assert_eq!(parse_size("M"), None);
+        assert_eq!(parse_size("99999999999999999999T"), None);
+    }
+
+    #[test]
+    fn parses_ages() {
+        assert_eq!(parse_age("30s"), Some(Duration::from_secs(30)));
+        assert_eq!(parse_age("15min"), Some(Duration::from_secs(15 * 60)));
+        assert_eq!(parse_age("15m"), Some(Duration::from_secs(15 * 60)));
+        assert_eq!(parse_age("12h"), Some(Duration::from_secs(12 * 60 * 60)));
+        assert_eq!(parse_age("7"), Some(Duration::from_secs(7 * DAY)));
+        assert_eq!(parse_age("2w"), Some(Duration::from_secs(14 * DAY)));
+        assert_eq!(parse_age("3mo"), Some(Duration::from_secs(90 * DAY)));
+        assert_eq!(parse_age("1y"), Some(Duration::from_secs(365 * DAY)));
+        assert_eq!(parse_age("1x"), None);
+        assert_eq!(parse_age("d"), None);
+        assert_eq!(parse_age("99999999999999999y"), None);
+        assert_eq!(parse_age("99999999999999999999"), None);
+    }
+
+    #[test]
+    fn parses_queries() {
+        let query = Query::parse(r#"type:image size:>2M modified:<7d "two words""#).unwrap();
+        assert_eq!(query.predicates.len(), 3);
+        assert_eq!(query.words.len(), 1);
+        assert!(matches!(
+            query.predicates[1],
+            Predicate::Size(Compare::Greater, 2097152)
+        ));
+
+        // only the known keys make a predicate
+        let query = Query::parse("notes:2024 C:foo").unwrap();
+        assert!(query.predicates.is_empty());
+        assert!(query.matches_name("old notes:2024 C:foo.txt"));
+        assert!(!query.matches_name("notes 2024"));
+    }
+
+    #[test]
+    fn rejects_bad_terms() {
+        let error = |input| Query::parse(input).unwrap_err().to_string();
+        assert_eq!(
+            error("modified:<99999999999999999y"),
+            "modified:<99999999999999999y: expected an age like <7d"
+        );
+        assert_eq!(error("size:big"), "size:big: expected a size like >2M");
+        assert_eq!(error("name:"), "name:: missing value");
+        assert_eq!(error(r#""open"#), r#""open: unclosed quote"#);
+    }
+}
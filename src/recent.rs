@@ -0,0 +1,282 @@
+use chrono::{DateTime, FixedOffset, SecondsFormat, Utc};
+use roxmltree::Document;
+
+use std::fs;
+use std::io;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+
+use crate::bookmarks::{path_to_uri, uri_to_path};
+use crate::dirfs::{read_fs_info, FsInfo};
+use crate::utils::{write_atomic, xdg_data_home};
+
+/// More files than this are not shown, the list is sorted by recency.
+const MAX_RECENT: usize = 100;
+
+const APP_NAME: &str = "iced-filechooser";
+
+const EMPTY_XBEL: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<xbel version="1.0"
+      xmlns:bookmark="http://www.freedesktop.org/standards/desktop-bookmarks"
+      xmlns:mime="http://www.freedesktop.org/standards/shared-mime-info"
+>
+</xbel>"#;
+
+/// Shared with every GTK application.
+pub fn recent_path() -> PathBuf {
+    xdg_data_home().join("recently-used.xbel")
+}
+
+fn escape_xml(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
+/// The `<bookmark>` elements of the xbel root, not `<bookmark:applications>`
+/// and such.
+fn bookmarks<'a, 'input>(
+    doc: &'a Document<'input>,
+) -> impl Iterator<Item = roxmltree::Node<'a, 'input>> {
+    doc.root_element().children().filter(|node| {
+        node.tag_name().name() == "bookmark" && node.tag_name().namespace().is_none()
+    })
+}
+
+/// The last time the file was touched by any application.
+fn last_used(node: &roxmltree::Node) -> Option<DateTime<FixedOffset>> {
+    ["modified", "visited", "added"]
+        .iter()
+        .filter_map(|attr| node.attribute(*attr))
+        .filter_map(|time| DateTime::parse_from_rfc3339(time).ok())
+        .max()
+}
+
+/// The local files of the xbel `content`, most recently used first.
+fn parse_recent(content: &str) -> Vec<PathBuf> {
+    let Ok(doc) = Document::parse(content) else {
+        return Vec::new();
+    };
+    let mut entries: Vec<(Option<DateTime<FixedOffset>>, PathBuf)> = bookmarks(&doc)
+        .filter_map(|node| Some((last_used(&node), uri_to_path(node.attribute("href")?)?)))
+        .collect();
+    entries.sort_by_key(|(time, _)| std::cmp::Reverse(*time));
+    entries.into_iter().map(|(_, path)| path).collect()
+}
+
+fn load_recent() -> Vec<FsInfo> {
+    let Ok(content) = fs::read_to_string(recent_path()) else {
+        return Vec::new();
+    };
+    parse_recent(&content)
+        .into_iter()
+        .filter_map(|path| {
+            // files removed since they were used are skipped
+            let metadata = fs::symlink_metadata(&path).ok()?;
+            let name = path.file_name()?.to_string_lossy().to_string();
+            read_fs_info(path, name, &metadata)
+        })
+        .filter(|info| info.is_file())
+        .take(MAX_RECENT)
+        .collect()
+}
+
+/// Lists the recently used files, most recent first.
+pub async fn recent_infos() -> Vec<FsInfo> {
+    tokio::task::spawn_blocking(load_recent)
+        .await
+        .unwrap_or_default()
+}
+
+fn new_bookmark(uri: &str, mime: &str, now: &str) -> String {
+    format!(
+        r#"  <bookmark href="{uri}" added="{now}" modified="{now}" visited="{now}">
+    <info>
+      <metadata owner="http://freedesktop.org">
+        <mime:mime-type type="{mime}"/>
+        <bookmark:applications>
+          <bookmark:application name="{APP_NAME}" exec="&apos;{APP_NAME} %u&apos;" modified="{now}" count="1"/>
+        </bookmark:applications>
+      </metadata>
+    </info>
+  </bookmark>
+"#,
+        uri = escape_xml(uri),
+        mime = escape_xml(mime),
+    )
+}
+
+/// Records the confirmed files in the xbel, entries already there only get
+/// their times updated so the metadata of other applications is kept.
+pub fn add_recent(paths: &[PathBuf]) -> io::Result<()> {
+    let file = recent_path();
+    let content = match fs::read_to_string(&file) {
+        Ok(content) => content,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => EMPTY_XBEL.to_string(),
+        Err(e) => return Err(e),
+    };
+    let now = Utc::now().to_rfc3339_opts(SecondsFormat::Micros, true);
+    match add_to_xbel(&content, paths, &now)? {
+        Some(content) => write_atomic(&file, content.as_bytes()),
+        None => Ok(()),
+    }
+}
+
+/// The xbel `content` with `paths` recorded as used at `now`, `None` if
+/// there is nothing to record. Everything else is kept as it was written.
+fn add_to_xbel(content: &str, paths: &[PathBuf], now: &str) -> io::Result<Option<String>> {
+    // a file we cannot parse is left alone rather than overwritten
+    let doc =
+        Document::parse(content).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+    let root = doc.root_element();
+    let Some(end) = content[..root.range().end].rfind("</xbel>") else {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, "no </xbel>"));
+    };
+    let mut edits: Vec<(Range<usize>, String)> = Vec::new();
+    let mut added = String::new();
+    for path in paths {
+        // URIs are absolute, what cannot be made so is left out
+        let Ok(path) = &path.canonicalize() else {
+            continue;
+        };
+        let Some(FsInfo::File { mimeinfo, .. }) = fs::metadata(path)
+            .ok()
+            .and_then(|metadata| read_fs_info(path.clone(), file_name(path), &metadata))
+        else {
+            continue;
+        };
+        let existing = bookmarks(&doc).find(|node| {
+            node.attribute("href")
+                .and_then(uri_to_path)
+                .is_some_and(|bookmarked| bookmarked == *path)
+        });
+        match existing {
+            Some(node) => {
+                for attr in node.attributes() {
+                    if matches!(attr.name(), "modified" | "visited") {
+                        edits.push((attr.range_value(), now.to_string()));
+                    }
+                }
+            }
+            None => {
+                let mime = mimeinfo
+                    .first()
+                    .map(|mime| mime.to_string())
+                    .unwrap_or("application/octet-stream".to_string());
+                added.push_str(&new_bookmark(&path_to_uri(path), &mime, now));
+            }
+        }
+    }
+    if edits.is_empty() && added.is_empty() {
+        return Ok(None);
+    }
+    edits.push((end..end, added));
+    let mut content = content.to_string();
+    edits.sort_by_key(|(range, _)| std::cmp::Reverse(range.start));
+    for (range, value) in edits {
+        content.replace_range(range, &value);
+    }
+    Ok(Some(content))
+}
+
+fn file_name(path: &Path) -> String {
+    path.file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NOW: &str = "2026-01-02T03:04:05.000000Z";
+
+    const XBEL: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<xbel version="1.0"
+      xmlns:bookmark="http://www.freedesktop.org/standards/desktop-bookmarks"
+      xmlns:mime="http://www.freedesktop.org/standards/shared-mime-info"
+>
+  <!-- written by another application -->
+  <bookmark href="file:///old.txt" added="2020-01-01T00:00:00Z" modified="2020-01-01T00:00:00Z" visited="2020-01-01T00:00:00Z"/>
+  <bookmark href="file:///new%20one.txt" added="2024-05-01T00:00:00Z"/>
+  <bookmark href="https://example.com/page" added="2025-01-01T00:00:00Z"/>
+  <folder><title>Kept</title></folder>
+  <bookmark href="file:///middle.txt" added="2019-01-01T00:00:00Z" visited="2022-01-01T00:00:00+02:00"/>
+</xbel>"#;
+
+    #[test]
+    fn reads_recent_files() {
+        assert_eq!(
+            parse_recent(XBEL),
+            [
+                PathBuf::from("/new one.txt"),
+                PathBuf::from("/middle.txt"),
+                PathBuf::from("/old.txt"),
+            ]
+        );
+        assert!(parse_recent("<xbel>").is_empty());
+    }
+
+    #[test]
+    fn adds_new_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("a & b.txt");
+        fs::write(&path, "text").unwrap();
+        let written = add_to_xbel(
+            EMPTY_XBEL,
+            &[path.clone(), dir.path().join("gone.txt")],
+            NOW,
+        )
+        .unwrap()
+        .unwrap();
+        assert_eq!(parse_recent(&written), [path.canonicalize().unwrap()]);
+
+        let doc = Document::parse(&written).unwrap();
+        let bookmark = bookmarks(&doc).next().unwrap();
+        assert_eq!(bookmark.attribute("added"), Some(NOW));
+        let mime = bookmark
+            .descendants()
+            .find(|node| node.tag_name().name() == "mime-type")
+            .unwrap();
+        assert_eq!(mime.attribute("type"), Some("text/plain"));
+        let application = bookmark
+            .descendants()
+            .find(|node| node.tag_name().name() == "application")
+            .unwrap();
+        assert_eq!(application.attribute("name"), Some(APP_NAME));
+
+        // nothing to record leaves the file as it is
+        assert!(add_to_xbel(&written, &[], NOW).unwrap().is_none());
+        assert!(add_to_xbel("<xbel", &[path], NOW).is_err());
+    }
+
+    #[test]
+    fn updates_existing_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().canonicalize().unwrap().join("used.txt");
+        fs::write(&path, "text").unwrap();
+        let existing = format!(
+            r#"<bookmark href="{}" added="2020-01-01T00:00:00Z" modified="2020-01-01T00:00:00Z" visited="2020-01-01T00:00:00Z"><info><metadata owner="other"><app/></metadata></info></bookmark>"#,
+            path_to_uri(&path)
+        );
+        let content = XBEL.replace("<folder>", &format!("{existing}\n  <folder>"));
+        let written = add_to_xbel(&content, std::slice::from_ref(&path), NOW)
+            .unwrap()
+            .unwrap();
+
+        let updated = existing
+            .replace(
+                r#"modified="2020-01-01T00:00:00Z""#,
+                &format!(r#"modified="{NOW}""#),
+            )
+            .replace(
+                r#"visited="2020-01-01T00:00:00Z""#,
+                &format!(r#"visited="{NOW}""#),
+            );
+        assert_eq!(written, content.replace(&existing, &updated));
+        assert_eq!(parse_recent(&written)[0], path);
+    }
+}
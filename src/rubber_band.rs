@@ -0,0 +1,272 @@
+use iced::advanced::layout::{self, Layout};
+use iced::advanced::renderer;
+use iced::advanced::widget::{tree, Operation, Tree};
+use iced::advanced::{overlay, Clipboard, Shell, Widget};
+use iced::{event, mouse};
+use iced::{
+    Border, Color, Element, Event, Length, Point, Rectangle, Renderer, Size, Theme, Vector,
+};
+
+use std::path::PathBuf;
+
+/// Selects the entries of a grid by dragging a rectangle over them.
+///
+/// The content is centered horizontally, the children of its layout are
+/// taken as the entries, in the order of `paths`, like the elements of an
+/// `iced_aw::Grid`. A drag only starts where the content ignores the press,
+/// so the buttons of the entries keep working.
+pub struct RubberBand<'a, Message> {
+    content: Element<'a, Message>,
+    paths: Vec<PathBuf>,
+    on_select: Box<dyn Fn(Vec<PathBuf>) -> Message + 'a>,
+    on_finish: Message,
+}
+
+#[derive(Debug, Default)]
+struct State {
+    origin: Option<Point>,
+    current: Option<Point>,
+    /// The indices selected by the last published message.
+    selected: Vec<usize>,
+}
+
+impl State {
+    fn rectangle(&self) -> Option<Rectangle> {
+        let (origin, current) = (self.origin?, self.current?);
+        let top_left = Point::new(origin.x.min(current.x), origin.y.min(current.y));
+        Some(Rectangle::new(
+            top_left,
+            Size::new((origin.x - current.x).abs(), (origin.y - current.y).abs()),
+        ))
+    }
+}
+
+impl<'a, Message> RubberBand<'a, Message> {
+    pub fn new(
+        content: impl Into<Element<'a, Message>>,
+        paths: Vec<PathBuf>,
+        on_select: impl Fn(Vec<PathBuf>) -> Message + 'a,
+        on_finish: Message,
+    ) -> Self {
+        Self {
+            content: content.into(),
+            paths,
+            on_select: Box::new(on_select),
+            on_finish,
+        }
+    }
+}
+
+impl<'a, Message: Clone> Widget<Message, Theme, Renderer> for RubberBand<'a, Message> {
+    fn tag(&self) -> tree::Tag {
+        tree::Tag::of::<State>()
+    }
+
+    fn state(&self) -> tree::State {
+        tree::State::new(State::default())
+    }
+
+    fn children(&self) -> Vec<Tree> {
+        vec![Tree::new(&self.content)]
+    }
+
+    fn diff(&self, tree: &mut Tree) {
+        tree.diff_children(std::slice::from_ref(&self.content));
+    }
+
+    fn size(&self) -> Size<Length> {
+        Size::new(Length::Fill, Length::Shrink)
+    }
+
+    fn layout(
+        &self,
+        tree: &mut Tree,
+        renderer: &Renderer,
+        limits: &layout::Limits,
+    ) -> layout::Node {
+        let content =
+            self.content
+                .as_widget()
+                .layout(&mut tree.children[0], renderer, &limits.loose());
+        let size = content.size();
+        let width = limits.max().width.max(size.width);
+        let x = (width - size.width) / 2.;
+        layout::Node::with_children(
+            Size::new(width, size.height),
+            vec![content.move_to(Point::new(x, 0.))],
+        )
+    }
+
+    fn draw(
+        &self,
+        tree: &Tree,
+        renderer: &mut Renderer,
+        theme: &Theme,
+        style: &renderer::Style,
+        layout: Layout<'_>,
+        cursor: mouse::Cursor,
+        viewport: &Rectangle,
+    ) {
+        let Some(content_layout) = layout.children().next() else {
+            return;
+        };
+        self.content.as_widget().draw(
+            &tree.children[0],
+            renderer,
+            theme,
+            style,
+            content_layout,
+            cursor,
+            viewport,
+        );
+        let Some(bounds) = tree.state.downcast_ref::<State>().rectangle() else {
+            return;
+        };
+        let primary = theme.palette().primary;
+        renderer::Renderer::fill_quad(
+            renderer,
+            renderer::Quad {
+                bounds,
+                border: Border {
+                    color: primary,
+                    width: 1.,
+                    radius: 0.into(),
+                },
+                ..Default::default()
+            },
+            Color { a: 0.2, ..primary },
+        );
+    }
+
+    fn operate(
+        &self,
+        tree: &mut Tree,
+        layout: Layout<'_>,
+        renderer: &Renderer,
+        operation: &mut dyn Operation<Message>,
+    ) {
+        let Some(content_layout) = layout.children().next() else {
+            return;
+        };
+        self.content.as_widget().operate(
+            &mut tree.children[0],
+            content_layout,
+            renderer,
+            operation,
+        );
+    }
+
+    fn on_event(
+        &mut self,
+        tree: &mut Tree,
+        event: Event,
+        layout: Layout<'_>,
+        cursor: mouse::Cursor,
+        renderer: &Renderer,
+        clipboard: &mut dyn Clipboard,
+        shell: &mut Shell<'_, Message>,
+        viewport: &Rectangle,
+    ) -> event::Status {
+        let Some(content_layout) = layout.children().next() else {
+            return event::Status::Ignored;
+        };
+        let status = self.content.as_widget_mut().on_event(
+            &mut tree.children[0],
+            event.clone(),
+            content_layout,
+            cursor,
+            renderer,
+            clipboard,
+            shell,
+            viewport,
+        );
+        let state = tree.state.downcast_mut::<State>();
+        match event {
+            Event::Mouse(mouse::Event::ButtonPressed(mouse::Button::Left))
+                if status == event::Status::Ignored =>
+            {
+                let Some(position) = cursor.position_over(layout.bounds()) else {
+                    return status;
+                };
+                state.origin = Some(position);
+                state.current = Some(position);
+                state.selected.clear();
+                event::Status::Captured
+            }
+            Event::Mouse(mouse::Event::CursorMoved { .. }) if state.origin.is_some() => {
+                let Some(position) = cursor.position() else {
+                    return status;
+                };
+                state.current = Some(position);
+                let Some(rectangle) = state.rectangle() else {
+                    return status;
+                };
+                let selected: Vec<usize> = content_layout
+                    .children()
+                    .enumerate()
+                    .filter(|(_, item)| item.bounds().intersects(&rectangle))
+                    .map(|(index, _)| index)
+                    .collect();
+                if selected != state.selected {
+                    let paths = selected
+                        .iter()
+                        .filter_map(|index| self.paths.get(*index).cloned())
+                        .collect();
+                    shell.publish((self.on_select)(paths));
+                    state.selected = selected;
+                }
+                event::Status::Captured
+            }
+            Event::Mouse(mouse::Event::ButtonReleased(mouse::Button::Left))
+                if state.origin.is_some() =>
+            {
+                *state = State::default();
+                shell.publish(self.on_finish.clone());
+                event::Status::Captured
+            }
+            _ => status,
+        }
+    }
+
+    fn mouse_interaction(
+        &self,
+        tree: &Tree,
+        layout: Layout<'_>,
+        cursor: mouse::Cursor,
+        viewport: &Rectangle,
+        renderer: &Renderer,
+    ) -> mouse::Interaction {
+        let Some(content_layout) = layout.children().next() else {
+            return mouse::Interaction::Idle;
+        };
+        self.content.as_widget().mouse_interaction(
+            &tree.children[0],
+            content_layout,
+            cursor,
+            viewport,
+            renderer,
+        )
+    }
+
+    fn overlay<'b>(
+        &'b mut self,
+        tree: &'b mut Tree,
+        layout: Layout<'_>,
+        renderer: &Renderer,
+        translation: Vector,
+    ) -> Option<overlay::Element<'b, Message, Theme, Renderer>> {
+        let content_layout = layout.children().next()?;
+        self.content.as_widget_mut().overlay(
+            &mut tree.children[0],
+            content_layout,
+            renderer,
+            translation,
+        )
+    }
+}
+
+impl<'a, Message: Clone + 'a> From<RubberBand<'a, Message>> for Element<'a, Message> {
+    fn from(rubber_band: RubberBand<'a, Message>) -> Self {
+        Element::new(rubber_band)
+    }
+}
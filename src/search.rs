@@ -0,0 +1,343 @@
+use iced::futures::channel::mpsc;
+use iced::futures::SinkExt;
+use iced::subscription;
+use iced::Subscription;
+
+use rayon::prelude::*;
+use regex::{Regex, RegexBuilder};
+
+use std::collections::{HashMap, VecDeque};
+use std::fmt::Display;
+use std::fs;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Arc;
+
+use crate::dirfs::{read_fs_info, FsInfo};
+use crate::portal_option::FileFilter;
+use crate::query::Query;
+use crate::Message;
+
+/// How many directory levels below the current directory are walked.
+pub const MAX_SEARCH_DEPTH: usize = 8;
+
+/// The walk stops once this many matches have been found.
+pub const MAX_SEARCH_RESULTS: usize = 500;
+
+/// Files bigger than this are not opened by the content search.
+pub const MAX_CONTENT_SIZE: u64 = 4 * 1024 * 1024;
+
+/// The mime types scanned by the content search by default.
+pub const CONTENT_SEARCH_MIME: &[&str] = &["text/*"];
+
+/// How many matching lines are kept for the preview pane.
+const MAX_SNIPPET_LINES: usize = 3;
+
+const MAX_SNIPPET_WIDTH: usize = 120;
+
+static SEARCH_ID: AtomicU64 = AtomicU64::new(0);
+
+/// Every search gets its own id, so results of a cancelled search can be told
+/// apart from the ones of the search replacing it.
+pub fn next_search_id() -> u64 {
+    SEARCH_ID.fetch_add(1, Ordering::Relaxed)
+}
+
+/// Where the search box looks for entries.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SearchScope {
+    #[default]
+    Folder,
+    Subfolders,
+    /// Query the filename index of [`crate::indexer`].
+    Everywhere,
+}
+
+impl SearchScope {
+    pub const ALL: [SearchScope; 3] = [
+        SearchScope::Folder,
+        SearchScope::Subfolders,
+        SearchScope::Everywhere,
+    ];
+}
+
+impl Display for SearchScope {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let scope = match self {
+            SearchScope::Folder => "This folder",
+            SearchScope::Subfolders => "Subfolders",
+            SearchScope::Everywhere => "Everywhere",
+        };
+        write!(f, "{scope}")
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum SearchKind {
+    /// Match a search box query against the entries.
+    Name(Query),
+    /// Match the bare words of the query fuzzily.
+    Fuzzy(Query),
+    /// Match the text inside the files.
+    Content(Regex),
+}
+
+impl SearchKind {
+    /// A pattern wrapped in slashes, like `/fn \w+/`, is a regex, anything
+    /// else is searched as a case insensitive substring.
+    pub fn content(pattern: &str) -> Result<Self, regex::Error> {
+        let regex = match pattern
+            .strip_prefix('/')
+            .and_then(|pattern| pattern.strip_suffix('/'))
+        {
+            Some(regex) if !regex.is_empty() => Regex::new(regex)?,
+            _ => RegexBuilder::new(&regex::escape(pattern))
+                .case_insensitive(true)
+                .build()?,
+        };
+        Ok(Self::Content(regex))
+    }
+}
+
+#[derive(Debug)]
+pub struct RecursiveSearch {
+    id: u64,
+    kind: SearchKind,
+    scope: SearchScope,
+    results: Vec<FsInfo>,
+    snippets: HashMap<PathBuf, String>,
+    finished: bool,
+}
+
+impl RecursiveSearch {
+    pub fn new(kind: SearchKind, scope: SearchScope) -> Self {
+        Self {
+            id: next_search_id(),
+            kind,
+            scope,
+            results: Vec::new(),
+            snippets: HashMap::new(),
+            finished: false,
+        }
+    }
+
+    pub fn id(&self) -> u64 {
+        self.id
+    }
+
+    pub fn kind(&self) -> &SearchKind {
+        &self.kind
+    }
+
+    pub fn scope(&self) -> SearchScope {
+        self.scope
+    }
+
+    pub fn results(&self) -> &[FsInfo] {
+        &self.results
+    }
+
+    pub fn snippet(&self, path: &PathBuf) -> Option<&str> {
+        self.snippets.get(path).map(|snippet| snippet.as_str())
+    }
+
+    pub fn append_results(&mut self, id: u64, results: Vec<(FsInfo, Option<String>)>) {
+        if id != self.id {
+            return;
+        }
+        for (info, snippet) in results {
+            if let Some(snippet) = snippet {
+                self.snippets.insert(info.path(), snippet);
+            }
+            self.results.push(info);
+        }
+    }
+
+    pub fn set_finished(&mut self, id: u64) {
+        if id == self.id {
+            self.finished = true;
+        }
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.finished
+    }
+
+    pub fn is_truncated(&self) -> bool {
+        self.results.len() >= MAX_SEARCH_RESULTS
+    }
+}
+
+/// Tells the content scanners to give up once the subscription is dropped.
+struct CancelOnDrop(Arc<AtomicBool>);
+
+impl Drop for CancelOnDrop {
+    fn drop(&mut self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+}
+
+fn is_content_searchable(info: &FsInfo) -> bool {
+    let FsInfo::File { mimeinfo, .. } = info else {
+        return false;
+    };
+    mimeinfo.iter().any(|mime| {
+        CONTENT_SEARCH_MIME.iter().any(|pattern| {
+            glob::Pattern::new(pattern).is_ok_and(|pattern| pattern.matches(mime.as_ref()))
+        })
+    })
+}
+
+/// Returns the matching lines of the file, or `None` if nothing matches.
+fn scan_content(info: &FsInfo, regex: &Regex) -> Option<String> {
+    let file = fs::File::open(info.path()).ok()?;
+    if file.metadata().ok()?.len() > MAX_CONTENT_SIZE {
+        return None;
+    }
+    let mut content = Vec::new();
+    file.take(MAX_CONTENT_SIZE).read_to_end(&mut content).ok()?;
+    // files with nul bytes are binary, whatever their name says
+    if content.iter().take(8192).any(|byte| *byte == 0) {
+        return None;
+    }
+    let content = String::from_utf8_lossy(&content);
+    let lines: Vec<String> = content
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| regex.is_match(line))
+        .take(MAX_SNIPPET_LINES)
+        .map(|(index, line)| {
+            let line: String = line.trim().chars().take(MAX_SNIPPET_WIDTH).collect();
+            format!("{}: {line}", index + 1)
+        })
+        .collect();
+    if lines.is_empty() {
+        return None;
+    }
+    Some(lines.join("\n"))
+}
+
+/// The entries of `dir` which are candidates for `kind`, and its
+/// subdirectories.
+fn list_dir(
+    dir: &Path,
+    kind: &SearchKind,
+    show_hide: bool,
+    filter: &FileFilter,
+) -> (Vec<FsInfo>, Vec<PathBuf>) {
+    let mut candidates = Vec::new();
+    let mut subdirs = Vec::new();
+    let Ok(entries) = fs::read_dir(dir) else {
+        return (candidates, subdirs);
+    };
+    for entry in entries.flatten() {
+        let Ok(name) = entry.file_name().into_string() else {
+            continue;
+        };
+        if !show_hide && name.starts_with('.') {
+            continue;
+        }
+        let Ok(metadata) = entry.metadata() else {
+            continue;
+        };
+        // symlinks are not followed, so the walk cannot loop
+        if metadata.is_dir() {
+            subdirs.push(entry.path());
+        }
+        if matches!(kind, SearchKind::Content(_)) && metadata.len() > MAX_CONTENT_SIZE {
+            continue;
+        }
+        let Some(info) = read_fs_info(entry.path(), name, &metadata) else {
+            continue;
+        };
+        if !info.is_match_filefilter(filter) {
+            continue;
+        }
+        let is_match = match kind {
+            SearchKind::Name(query) => query.matches(&info),
+            SearchKind::Fuzzy(query) => query.fuzzy_match(&info).is_some(),
+            SearchKind::Content(_) => true,
+        };
+        if is_match {
+            candidates.push(info);
+        }
+    }
+    (candidates, subdirs)
+}
+
+/// Walks below `root` and streams every entry matching `kind`.
+///
+/// The walk lives as long as the subscription does, so dropping it from
+/// [`crate::FileChooser`]'s subscription cancels the search.
+pub fn recursive_search(
+    id: u64,
+    root: PathBuf,
+    kind: SearchKind,
+    show_hide: bool,
+    filter: FileFilter,
+) -> Subscription<Message> {
+    subscription::channel(
+        (std::any::TypeId::of::<RecursiveSearch>(), id),
+        100,
+        move |mut output: mpsc::Sender<Message>| async move {
+            let (kind, filter) = (Arc::new(kind), Arc::new(filter));
+            let cancelled = CancelOnDrop(Arc::new(AtomicBool::new(false)));
+            let mut found = 0;
+            let mut pending = VecDeque::from([(root, 0)]);
+            while let Some((dir, depth)) = pending.pop_front() {
+                if found >= MAX_SEARCH_RESULTS {
+                    break;
+                }
+                // listing a directory blocks, on a slow disk or mount for long
+                let listing = tokio::task::spawn_blocking({
+                    let (kind, filter) = (kind.clone(), filter.clone());
+                    move || list_dir(&dir, &kind, show_hide, &filter)
+                })
+                .await;
+                let Ok((candidates, subdirs)) = listing else {
+                    continue;
+                };
+                if depth + 1 < MAX_SEARCH_DEPTH {
+                    pending.extend(subdirs.into_iter().map(|subdir| (subdir, depth + 1)));
+                }
+                let mut matches: Vec<(FsInfo, Option<String>)> = match kind.as_ref() {
+                    SearchKind::Name(_) | SearchKind::Fuzzy(_) => {
+                        candidates.into_iter().map(|info| (info, None)).collect()
+                    }
+                    SearchKind::Content(regex) => {
+                        let regex = regex.clone();
+                        let cancelled = cancelled.0.clone();
+                        tokio::task::spawn_blocking(move || {
+                            candidates
+                                .into_par_iter()
+                                .filter(is_content_searchable)
+                                .filter_map(|info| {
+                                    if cancelled.load(Ordering::Relaxed) {
+                                        return None;
+                                    }
+                                    let snippet = scan_content(&info, &regex)?;
+                                    Some((info, Some(snippet)))
+                                })
+                                .collect()
+                        })
+                        .await
+                        .unwrap_or_default()
+                    }
+                };
+                matches.truncate(MAX_SEARCH_RESULTS - found);
+                found += matches.len();
+                if !matches.is_empty() {
+                    let _ = output
+                        .send(Message::SearchResultsFound((id, matches)))
+                        .await;
+                }
+                tokio::task::yield_now().await;
+            }
+            let _ = output.send(Message::SearchFinished(id)).await;
+            loop {
+                std::future::pending::<()>().await;
+            }
+        },
+    )
+}
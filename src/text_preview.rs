@@ -0,0 +1,282 @@
+use std::fs::File;
+use std::io::Read;
+use std::os::unix::fs::OpenOptionsExt;
+use std::path::{Path, PathBuf};
+
+use mime::Mime;
+
+use crate::hex_dump::{HexDump, DUMP_BYTES};
+use crate::highlight::{highlight, HighlightedLine};
+
+/// Only the start of a file is read for its preview.
+pub const PREVIEW_BYTES: usize = 32 * 1024;
+
+/// Source and config types which shared-mime-info files under `application/`.
+const TEXT_APPLICATION_TYPES: &[&str] = &[
+    "json",
+    "xml",
+    "javascript",
+    "ecmascript",
+    "sql",
+    "toml",
+    "yaml",
+    "x-yaml",
+    "x-shellscript",
+    "x-perl",
+    "x-php",
+    "x-ruby",
+    "x-python",
+    "x-awk",
+    "x-desktop",
+    "x-subrip",
+    "x-wine-extension-ini",
+    "pgp-keys",
+];
+
+/// Part of a file as shown beside the grid, as text when it is some and as
+/// a hex dump otherwise.
+#[derive(Debug, Clone)]
+pub enum TextPreview {
+    Text {
+        text: String,
+        encoding: &'static str,
+        /// Whether the file goes on after [`PREVIEW_BYTES`].
+        truncated: bool,
+        /// The first lines, if the syntax of the file is known.
+        highlighted: Option<Vec<HighlightedLine>>,
+    },
+    Binary(HexDump),
+}
+
+pub fn is_text_type(mime_types: &[Mime]) -> bool {
+    mime_types.iter().any(|mime| {
+        let suffix = mime.suffix().map(|suffix| suffix.as_str());
+        mime.type_() == mime::TEXT
+            || matches!(suffix, Some("xml" | "json"))
+            || (mime.type_() == mime::APPLICATION
+                && TEXT_APPLICATION_TYPES.contains(&mime.subtype().as_str()))
+    })
+}
+
+fn decode_utf16(bytes: &[u8], from_bytes: fn([u8; 2]) -> u16) -> String {
+    let units = bytes
+        .chunks_exact(2)
+        .map(|pair| from_bytes([pair[0], pair[1]]));
+    char::decode_utf16(units)
+        .map(|c| c.unwrap_or(char::REPLACEMENT_CHARACTER))
+        .collect()
+}
+
+/// Text has no NUL bytes and few control characters besides whitespace.
+fn looks_binary(bytes: &[u8]) -> bool {
+    if bytes.contains(&0) {
+        return true;
+    }
+    let control = bytes
+        .iter()
+        .filter(|byte| byte.is_ascii_control() && !b"\t\n\r\x0c\x1b".contains(byte))
+        .count();
+    control * 10 > bytes.len()
+}
+
+/// Decodes the start of a file, `truncated` when `bytes` stops short of its
+/// end, which may be in the middle of a character, `None` if it is binary.
+fn decode(bytes: &[u8], truncated: bool) -> Option<TextPreview> {
+    let text = |text: String, encoding| {
+        Some(TextPreview::Text {
+            text,
+            encoding,
+            truncated,
+            highlighted: None,
+        })
+    };
+    if let Some(rest) = bytes.strip_prefix(b"\xef\xbb\xbf") {
+        return text(String::from_utf8_lossy(rest).into_owned(), "UTF-8");
+    }
+    if let Some(rest) = bytes.strip_prefix(b"\xff\xfe") {
+        return text(decode_utf16(rest, u16::from_le_bytes), "UTF-16LE");
+    }
+    if let Some(rest) = bytes.strip_prefix(b"\xfe\xff") {
+        return text(decode_utf16(rest, u16::from_be_bytes), "UTF-16BE");
+    }
+    if looks_binary(bytes) {
+        return None;
+    }
+    match std::str::from_utf8(bytes) {
+        Ok(utf8) => text(utf8.to_string(), "UTF-8"),
+        // a character cut at the end of what was read
+        Err(error) if truncated && error.error_len().is_none() => text(
+            String::from_utf8_lossy(&bytes[..error.valid_up_to()]).into_owned(),
+            "UTF-8",
+        ),
+        // every byte is a character in Latin-1
+        Err(_) => text(
+            bytes.iter().map(|&byte| byte as char).collect(),
+            "ISO-8859-1",
+        ),
+    }
+}
+
+/// The start of a text file, or of one of unknown type which looks like
+/// text, as text, and of any other file, or of one which only claims to be
+/// text, as a hex dump.
+fn read_start(path: &Path, mime_types: &[Mime]) -> Option<TextPreview> {
+    // a FIFO put in place of the file would block the open
+    let file = File::options()
+        .read(true)
+        .custom_flags(libc::O_NONBLOCK)
+        .open(path)
+        .ok()?;
+    let metadata = file.metadata().ok()?;
+    if !metadata.is_file() {
+        return None;
+    }
+    let size = metadata.len();
+    let mut bytes = Vec::with_capacity(DUMP_BYTES);
+    file.take(DUMP_BYTES as u64).read_to_end(&mut bytes).ok()?;
+    // files without a known type, like LICENSE or .gitignore, are text as
+    // often as not
+    let unknown = mime_types
+        .iter()
+        .all(|mime| *mime == mime::APPLICATION_OCTET_STREAM);
+    let text = (unknown || is_text_type(mime_types))
+        .then(|| {
+            let truncated = bytes.len() > PREVIEW_BYTES;
+            decode(&bytes[..bytes.len().min(PREVIEW_BYTES)], truncated)
+        })
+        .flatten();
+    Some(text.unwrap_or_else(|| TextPreview::Binary(HexDump::new(bytes, size))))
+}
+
+/// Reads the start of `path` off the UI thread, highlighted if it is text.
+pub async fn read_text_preview(
+    path: PathBuf,
+    mime_types: Vec<Mime>,
+    dark: bool,
+) -> (PathBuf, Option<TextPreview>) {
+    let preview = tokio::task::spawn_blocking({
+        let path = path.clone();
+        move || {
+            let mut preview = read_start(&path, &mime_types)?;
+            if let TextPreview::Text {
+                text, highlighted, ..
+            } = &mut preview
+            {
+                *highlighted = highlight(text, &path, &mime_types, dark);
+            }
+            Some(preview)
+        }
+    })
+    .await
+    .ok()
+    .flatten();
+    (path, preview)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn decoded(bytes: &[u8], truncated: bool) -> Option<(String, &'static str)> {
+        match decode(bytes, truncated)? {
+            TextPreview::Text { text, encoding, .. } => Some((text, encoding)),
+            TextPreview::Binary(_) => None,
+        }
+    }
+
+    #[test]
+    fn detects_encodings() {
+        let text = |text: &str, encoding| Some((text.to_string(), encoding));
+        assert_eq!(
+            decoded("grüße\n".as_bytes(), false),
+            text("grüße\n", "UTF-8")
+        );
+        assert_eq!(decoded(b"\xef\xbb\xbfbom", false), text("bom", "UTF-8"));
+        assert_eq!(decoded(b"\xff\xfeh\0i\0", false), text("hi", "UTF-16LE"));
+        assert_eq!(decoded(b"\xfe\xff\0h\0i", false), text("hi", "UTF-16BE"));
+        assert_eq!(decoded(b"gr\xfc\xdfe", false), text("grüße", "ISO-8859-1"));
+    }
+
+    #[test]
+    fn cuts_truncated_characters() {
+        // the first two of the three bytes of a euro sign
+        assert_eq!(
+            decoded(b"5 \xe2\x82", true),
+            Some(("5 ".to_string(), "UTF-8"))
+        );
+        assert_eq!(
+            decoded(b"5 \xe2\x82", false),
+            Some(("5 \u{e2}\u{82}".to_string(), "ISO-8859-1"))
+        );
+    }
+
+    #[test]
+    fn tells_binary() {
+        assert!(looks_binary(b"text\0with a nul"));
+        assert!(looks_binary(b"\x01\x02\x03 mostly control"));
+        assert!(!looks_binary(
+            b"tabs\tnewlines\r\n\x1b[1mescapes\x1b[0m\x0c"
+        ));
+        assert!(!looks_binary(b"one \x07 bell in many more characters"));
+        assert!(!looks_binary(b""));
+        assert_eq!(decoded(b"\x7fELF\x02\x01\x01\0\0", false), None);
+    }
+
+    #[test]
+    fn reads_the_start() {
+        let dir = tempfile::tempdir().unwrap();
+        let long = dir.path().join("long.txt");
+        std::fs::write(&long, "line\n".repeat(PREVIEW_BYTES)).unwrap();
+        let Some(TextPreview::Text {
+            text, truncated, ..
+        }) = read_start(&long, &[mime::TEXT_PLAIN])
+        else {
+            panic!("not read as text");
+        };
+        assert!(truncated);
+        assert_eq!(text.len(), PREVIEW_BYTES);
+
+        let unknown = dir.path().join("LICENSE");
+        std::fs::write(&unknown, "Permission is granted").unwrap();
+        assert!(matches!(
+            read_start(&unknown, &[mime::APPLICATION_OCTET_STREAM]),
+            Some(TextPreview::Text {
+                truncated: false,
+                ..
+            })
+        ));
+
+        // claims to be text
+        let binary = dir.path().join("binary.txt");
+        std::fs::write(&binary, [0; 100]).unwrap();
+        let Some(TextPreview::Binary(dump)) = read_start(&binary, &[mime::TEXT_PLAIN]) else {
+            panic!("not read as binary");
+        };
+        assert_eq!((dump.shown_bytes(), dump.size), (100, 100));
+
+        let image = dir.path().join("image.png");
+        std::fs::write(&image, "not really").unwrap();
+        assert!(matches!(
+            read_start(&image, &[mime::IMAGE_PNG]),
+            Some(TextPreview::Binary(_))
+        ));
+
+        assert!(read_start(dir.path(), &[mime::TEXT_PLAIN]).is_none());
+        assert!(read_start(&dir.path().join("missing"), &[mime::TEXT_PLAIN]).is_none());
+    }
+
+    #[test]
+    fn tells_text_types() {
+        let types =
+            |types: &[&str]| -> Vec<Mime> { types.iter().map(|t| t.parse().unwrap()).collect() };
+        assert!(is_text_type(&types(&["text/x-rust"])));
+        assert!(is_text_type(&types(&["application/json"])));
+        assert!(is_text_type(&types(&["image/svg+xml"])));
+        assert!(is_text_type(&types(&[
+            "application/zip",
+            "application/x-shellscript"
+        ])));
+        assert!(!is_text_type(&types(&["application/zip"])));
+        assert!(!is_text_type(&[]));
+    }
+}
@@ -0,0 +1,388 @@
+use std::collections::HashSet;
+use std::env;
+use std::ffi::OsString;
+use std::os::unix::ffi::OsStrExt;
+use std::os::unix::process::CommandExt;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::sync::LazyLock;
+use std::time::{Duration, Instant};
+
+use image::DynamicImage;
+use mime::Mime;
+
+use crate::bookmarks::path_to_uri;
+use crate::config::CONFIG;
+use crate::keyfile::KeyFile;
+use crate::utils::{xdg_data_dirs, xdg_data_home};
+
+const ENTRY_GROUP: &str = "Thumbnailer Entry";
+
+/// A thumbnailer still running after this is killed.
+const THUMBNAILER_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// How often a running thumbnailer is checked on.
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// Address space allowed to a thumbnailer which runs without bubblewrap.
+const MEMORY_LIMIT: libc::rlim_t = 2 * 1024 * 1024 * 1024;
+
+static THUMBNAILERS: LazyLock<Vec<Thumbnailer>> = LazyLock::new(load_thumbnailers);
+
+/// Whether thumbnailers can be run in a bubblewrap sandbox.
+static BWRAP: LazyLock<Option<PathBuf>> = LazyLock::new(|| find_program("bwrap"));
+
+/// A `.thumbnailer` file, as installed by totem, evince, gnome-desktop and
+/// others to make thumbnails of the types they open.
+#[derive(Debug)]
+struct Thumbnailer {
+    exec: Vec<String>,
+    mime_types: Vec<String>,
+}
+
+fn find_program(name: &str) -> Option<PathBuf> {
+    if name.contains('/') {
+        return Path::new(name).is_file().then(|| PathBuf::from(name));
+    }
+    env::split_paths(&env::var_os("PATH")?)
+        .map(|dir| dir.join(name))
+        .find(|path| path.is_file())
+}
+
+/// Splits an `Exec` line into arguments, following the quoting of desktop
+/// entries.
+fn split_exec(exec: &str) -> Vec<String> {
+    let mut args = Vec::new();
+    let mut current = String::new();
+    let mut in_arg = false;
+    let mut quoted = false;
+    let mut chars = exec.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' => {
+                quoted = !quoted;
+                in_arg = true;
+            }
+            '\\' if quoted => current.extend(chars.next()),
+            c if c.is_whitespace() && !quoted => {
+                if in_arg {
+                    args.push(std::mem::take(&mut current));
+                    in_arg = false;
+                }
+            }
+            c => {
+                current.push(c);
+                in_arg = true;
+            }
+        }
+    }
+    if in_arg {
+        args.push(current);
+    }
+    args
+}
+
+impl Thumbnailer {
+    fn parse(keyfile: &KeyFile) -> Option<Self> {
+        if let Some(try_exec) = keyfile.get(ENTRY_GROUP, "TryExec") {
+            find_program(try_exec)?;
+        }
+        let exec = split_exec(keyfile.get(ENTRY_GROUP, "Exec")?);
+        if exec.is_empty() {
+            return None;
+        }
+        Some(Self {
+            exec,
+            mime_types: keyfile.get_list(ENTRY_GROUP, "MimeType"),
+        })
+    }
+
+    fn handles(&self, mime: &Mime) -> bool {
+        self.mime_types
+            .iter()
+            .any(|handled| handled.eq_ignore_ascii_case(mime.essence_str()))
+    }
+
+    /// The arguments with `%i`, `%o`, `%s` and `%u` replaced.
+    fn args(&self, input: &Path, output: &Path, size: u32) -> Vec<OsString> {
+        self.exec
+            .iter()
+            .map(|arg| {
+                let mut expanded = OsString::new();
+                let mut chars = arg.chars();
+                while let Some(c) = chars.next() {
+                    if c != '%' {
+                        expanded.push(c.encode_utf8(&mut [0; 4]));
+                        continue;
+                    }
+                    match chars.next() {
+                        Some('i') => expanded.push(input),
+                        Some('o') => expanded.push(output),
+                        Some('s') => expanded.push(size.to_string()),
+                        Some('u') => expanded.push(path_to_uri(input)),
+                        Some('%') => expanded.push("%"),
+                        _ => {}
+                    }
+                }
+                expanded
+            })
+            .collect()
+    }
+
+    /// Runs the thumbnailer without network, with `/` read only and only
+    /// `output_dir` writable when run in bubblewrap `bwrap`, and with limits
+    /// on memory and CPU time in any case.
+    fn command(
+        &self,
+        input: &Path,
+        output_dir: &Path,
+        output: &Path,
+        size: u32,
+        bwrap: Option<&Path>,
+    ) -> Command {
+        let args = self.args(input, output, size);
+        let mut command = match bwrap {
+            Some(bwrap) => {
+                let mut command = Command::new(bwrap);
+                command
+                    .args(["--ro-bind", "/", "/", "--dev", "/dev", "--proc", "/proc"])
+                    .args(["--tmpfs", "/tmp"])
+                    // after the tmpfs, which would hide them if under /tmp
+                    .arg("--ro-bind")
+                    .args([input, input])
+                    .arg("--bind")
+                    .args([output_dir, output_dir])
+                    .args(["--unshare-all", "--die-with-parent", "--new-session"])
+                    .arg("--")
+                    .args(&args);
+                command
+            }
+            None => {
+                let mut command = Command::new(&args[0]);
+                command.args(&args[1..]);
+                command
+            }
+        };
+        command
+            .env_clear()
+            .envs(env::var_os("PATH").map(|path| ("PATH", path)))
+            .current_dir(output_dir)
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            // to kill whatever it started along with it
+            .process_group(0);
+        let cpu_seconds = THUMBNAILER_TIMEOUT.as_secs() as libc::rlim_t;
+        // the sandbox is protection enough, and bubblewrap needs more address
+        // space to set it up than a thumbnailer to run
+        let limit_memory = bwrap.is_none();
+        unsafe {
+            command.pre_exec(move || {
+                let memory = libc::rlimit {
+                    rlim_cur: MEMORY_LIMIT,
+                    rlim_max: MEMORY_LIMIT,
+                };
+                let cpu = libc::rlimit {
+                    rlim_cur: cpu_seconds,
+                    rlim_max: cpu_seconds,
+                };
+                if limit_memory {
+                    libc::setrlimit(libc::RLIMIT_AS, &memory);
+                }
+                libc::setrlimit(libc::RLIMIT_CPU, &cpu);
+                Ok(())
+            });
+        }
+        command
+    }
+
+    /// Runs the thumbnailer, killed with everything it started after
+    /// `timeout`.
+    fn run(
+        &self,
+        input: &Path,
+        size: u32,
+        bwrap: Option<&Path>,
+        timeout: Duration,
+    ) -> Option<DynamicImage> {
+        let output_dir = tempfile::tempdir().ok()?;
+        let output = output_dir.path().join("thumbnail.png");
+        let mut child = self
+            .command(input, output_dir.path(), &output, size, bwrap)
+            .spawn()
+            .ok()?;
+        let started = Instant::now();
+        let status = loop {
+            if let Some(status) = child.try_wait().ok()? {
+                break status;
+            }
+            if started.elapsed() > timeout {
+                unsafe { libc::kill(-(child.id() as libc::pid_t), libc::SIGKILL) };
+                let _ = child.wait();
+                return None;
+            }
+            std::thread::sleep(POLL_INTERVAL);
+        };
+        if !status.success() {
+            return None;
+        }
+        image::open(&output).ok()
+    }
+}
+
+/// The thumbnailers in the user's data dir come before, and hide, the ones of
+/// the same name in the system's.
+fn load_thumbnailers() -> Vec<Thumbnailer> {
+    let mut seen = HashSet::new();
+    let mut thumbnailers = Vec::new();
+    let dirs = std::iter::once(xdg_data_home()).chain(xdg_data_dirs());
+    for dir in dirs.map(|dir| dir.join("thumbnailers")) {
+        let Ok(entries) = std::fs::read_dir(dir) else {
+            continue;
+        };
+        for entry in entries.flatten() {
+            let name = entry.file_name();
+            if !name.as_bytes().ends_with(b".thumbnailer") || !seen.insert(name) {
+                continue;
+            }
+            let Some(keyfile) = KeyFile::open(entry.path()) else {
+                continue;
+            };
+            thumbnailers.extend(Thumbnailer::parse(&keyfile));
+        }
+    }
+    thumbnailers
+}
+
+fn find_thumbnailer(mime_types: &[Mime]) -> Option<&'static Thumbnailer> {
+    THUMBNAILERS
+        .iter()
+        .find(|thumbnailer| mime_types.iter().any(|mime| thumbnailer.handles(mime)))
+}
+
+/// Thumbnailers are only run in bubblewrap, unless the user agreed to run
+/// them without, see [`crate::config::Config::unsandboxed_thumbnailers`].
+fn can_run_thumbnailers() -> bool {
+    BWRAP.is_some() || CONFIG.unsandboxed_thumbnailers
+}
+
+pub fn has_thumbnailer(mime_types: &[Mime]) -> bool {
+    can_run_thumbnailers() && find_thumbnailer(mime_types).is_some()
+}
+
+/// Makes a thumbnail of `input` with the thumbnailer installed for its type,
+/// `None` if there is none or it failed.
+pub fn run_thumbnailer(input: &Path, mime_types: &[Mime], size: u32) -> Option<DynamicImage> {
+    if !can_run_thumbnailers() {
+        return None;
+    }
+    let thumbnailer = find_thumbnailer(mime_types)?;
+    // the thumbnailer runs in another directory, and `%u` must be a full URI
+    let input = input.canonicalize().ok()?;
+    thumbnailer.run(&input, size, BWRAP.as_deref(), THUMBNAILER_TIMEOUT)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::os::unix::fs::PermissionsExt;
+
+    use super::*;
+
+    /// A thumbnailer running `script` with `exec` as its arguments, written
+    /// to `dir`.
+    fn fake_thumbnailer(dir: &Path, script: &str, exec: &str) -> Thumbnailer {
+        let path = dir.join("fake-thumbnailer");
+        std::fs::write(&path, format!("#!/bin/sh\n{script}\n")).unwrap();
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755)).unwrap();
+        Thumbnailer {
+            exec: split_exec(&format!("{} {exec}", path.display())),
+            mime_types: vec!["application/x-fake".to_string()],
+        }
+    }
+
+    #[test]
+    fn splits_exec_with_quotes() {
+        assert_eq!(
+            split_exec(r#"thumb  -s %s "%i" "a \"quoted\" arg" %o"#),
+            ["thumb", "-s", "%s", "%i", r#"a "quoted" arg"#, "%o"]
+        );
+        assert_eq!(split_exec(r#"thumb """#), ["thumb", ""]);
+        assert!(split_exec("   ").is_empty());
+    }
+
+    #[test]
+    fn expands_field_codes() {
+        let thumbnailer = Thumbnailer {
+            exec: split_exec("thumb -s %s %i %u --out=%o 100%%"),
+            mime_types: Vec::new(),
+        };
+        let args = thumbnailer.args(Path::new("/tmp/a b.mp4"), Path::new("/out/t.png"), 128);
+        assert_eq!(
+            args,
+            [
+                "thumb",
+                "-s",
+                "128",
+                "/tmp/a b.mp4",
+                "file:///tmp/a%20b.mp4",
+                "--out=/out/t.png",
+                "100%",
+            ]
+        );
+    }
+
+    #[test]
+    fn reads_the_thumbnail_written() {
+        let dir = tempfile::tempdir().unwrap();
+        let input = dir.path().join("input.png");
+        image::RgbaImage::new(3, 2).save(&input).unwrap();
+        let thumbnailer = fake_thumbnailer(dir.path(), r#"cp "$1" "$2""#, "%i %o");
+        let image = thumbnailer
+            .run(&input, 128, None, THUMBNAILER_TIMEOUT)
+            .unwrap();
+        assert_eq!((image.width(), image.height()), (3, 2));
+    }
+
+    #[test]
+    fn fails_without_output() {
+        let dir = tempfile::tempdir().unwrap();
+        let thumbnailer = fake_thumbnailer(dir.path(), "exit 0", "%i %o");
+        assert!(thumbnailer
+            .run(dir.path(), 128, None, THUMBNAILER_TIMEOUT)
+            .is_none());
+        let thumbnailer = fake_thumbnailer(dir.path(), "exit 1", "%i %o");
+        assert!(thumbnailer
+            .run(dir.path(), 128, None, THUMBNAILER_TIMEOUT)
+            .is_none());
+    }
+
+    #[test]
+    fn kills_thumbnailers_running_too_long() {
+        let dir = tempfile::tempdir().unwrap();
+        let pid_file = dir.path().join("pid");
+        let script = format!(r#"sleep 30 & echo $! > "{}"; wait"#, pid_file.display());
+        let thumbnailer = fake_thumbnailer(dir.path(), &script, "%i %o");
+        let started = Instant::now();
+        let image = thumbnailer.run(dir.path(), 128, None, Duration::from_millis(300));
+        assert!(image.is_none());
+        assert!(started.elapsed() < Duration::from_secs(5));
+        // what the thumbnailer started is gone along with it
+        let pid: libc::pid_t = std::fs::read_to_string(&pid_file)
+            .unwrap()
+            .trim()
+            .parse()
+            .unwrap();
+        std::thread::sleep(Duration::from_millis(100));
+        // gone, or a zombie waiting for whoever it was left to
+        let stat = std::fs::read_to_string(format!("/proc/{pid}/stat")).unwrap_or_default();
+        assert!(
+            stat.is_empty()
+                || stat
+                    .rsplit(')')
+                    .next()
+                    .unwrap()
+                    .trim_start()
+                    .starts_with('Z')
+        );
+    }
+}
@@ -0,0 +1,276 @@
+use std::fs::{self, File};
+use std::io::BufWriter;
+use std::os::unix::fs::DirBuilderExt;
+use std::path::{Path, PathBuf};
+use std::sync::LazyLock;
+use std::time::UNIX_EPOCH;
+
+use mime::Mime;
+
+use crate::bookmarks::path_to_uri;
+use crate::image_info::open_oriented;
+use crate::thumbnailers::run_thumbnailer;
+use crate::utils::{write_atomic, xdg_cache_home};
+
+/// Where failed attempts are recorded, so broken files aren't retried on
+/// every visit. The spec wants one directory per program and version.
+const FAIL_DIR: &str = concat!("fail/iced-filechooser-", env!("CARGO_PKG_VERSION"));
+
+static POOL: LazyLock<rayon::ThreadPool> = LazyLock::new(|| {
+    let threads = std::thread::available_parallelism()
+        .map(|threads| threads.get() / 2)
+        .unwrap_or(1)
+        .max(1);
+    rayon::ThreadPoolBuilder::new()
+        .num_threads(threads)
+        .thread_name(|index| format!("thumbnailer-{index}"))
+        .build()
+        .expect("the thumbnail pool should start")
+});
+
+/// The sizes of the Thumbnail Managing Standard.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ThumbnailSize {
+    Normal,
+    Large,
+}
+
+impl ThumbnailSize {
+    fn dir(self) -> &'static str {
+        match self {
+            Self::Normal => "normal",
+            Self::Large => "large",
+        }
+    }
+
+    pub fn pixels(self) -> u32 {
+        match self {
+            Self::Normal => 128,
+            Self::Large => 256,
+        }
+    }
+}
+
+fn thumbnails_dir() -> PathBuf {
+    xdg_cache_home().join("thumbnails")
+}
+
+/// Thumbnails are named after the md5 of the URI of the original.
+fn thumbnail_name(uri: &str) -> String {
+    format!("{:x}.png", md5::compute(uri))
+}
+
+fn mtime(path: &Path) -> Option<u64> {
+    let modified = fs::metadata(path).ok()?.modified().ok()?;
+    Some(modified.duration_since(UNIX_EPOCH).ok()?.as_secs())
+}
+
+/// A thumbnail only counts if it was made from the current version of the
+/// original, which `Thumb::URI` and `Thumb::MTime` tell.
+fn is_valid(thumbnail: &Path, uri: &str, mtime: u64) -> bool {
+    let Ok(file) = File::open(thumbnail) else {
+        return false;
+    };
+    let Ok(reader) = png::Decoder::new(file).read_info() else {
+        return false;
+    };
+    let info = reader.info();
+    let text = |key: &str| {
+        info.uncompressed_latin1_text
+            .iter()
+            .find(|chunk| chunk.keyword == key)
+            .map(|chunk| chunk.text.clone())
+            .or_else(|| {
+                info.utf8_text
+                    .iter()
+                    .find(|chunk| chunk.keyword == key)
+                    .and_then(|chunk| chunk.get_text().ok())
+            })
+    };
+    text("Thumb::URI").as_deref() == Some(uri)
+        && text("Thumb::MTime").is_some_and(|text| text.parse() == Ok(mtime))
+}
+
+fn write_thumbnail(
+    path: &Path,
+    (width, height): (u32, u32),
+    pixels: &[u8],
+    uri: &str,
+    mtime: u64,
+) -> Option<()> {
+    let mut png_data = Vec::new();
+    let mut encoder = png::Encoder::new(BufWriter::new(&mut png_data), width, height);
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+    encoder
+        .add_text_chunk("Thumb::URI".to_string(), uri.to_string())
+        .ok()?;
+    encoder
+        .add_text_chunk("Thumb::MTime".to_string(), mtime.to_string())
+        .ok()?;
+    encoder
+        .add_text_chunk("Software".to_string(), "iced-filechooser".to_string())
+        .ok()?;
+    let mut writer = encoder.write_header().ok()?;
+    writer.write_image_data(pixels).ok()?;
+    writer.finish().ok()?;
+    // only the user may read thumbnails, as the spec asks, the temporary
+    // file written through is created with 0600 already
+    fs::DirBuilder::new()
+        .recursive(true)
+        .mode(0o700)
+        .create(path.parent()?)
+        .ok()?;
+    write_atomic(path, &png_data).ok()
+}
+
+/// A valid thumbnail of `path` which is already in `cache_dir`.
+fn cached_thumbnail(cache_dir: &Path, path: &Path, size: ThumbnailSize) -> Option<PathBuf> {
+    let uri = path_to_uri(path);
+    let mtime = mtime(path)?;
+    let name = thumbnail_name(&uri);
+    // a large thumbnail does for a normal one too
+    [size, ThumbnailSize::Large]
+        .into_iter()
+        .map(|size| cache_dir.join(size.dir()).join(&name))
+        .find(|thumbnail| is_valid(thumbnail, &uri, mtime))
+}
+
+fn has_failed(cache_dir: &Path, path: &Path) -> bool {
+    let uri = path_to_uri(path);
+    let Some(mtime) = mtime(path) else {
+        return true;
+    };
+    let fail = cache_dir.join(FAIL_DIR).join(thumbnail_name(&uri));
+    is_valid(&fail, &uri, mtime)
+}
+
+/// Images are decoded here, other types are left to the thumbnailers. The
+/// thumbnail, or the mark of the failure, is written to `cache_dir`.
+fn generate(
+    cache_dir: &Path,
+    path: &Path,
+    mime_types: &[Mime],
+    size: ThumbnailSize,
+) -> Option<PathBuf> {
+    let uri = path_to_uri(path);
+    let mtime = mtime(path)?;
+    let name = thumbnail_name(&uri);
+    let image = open_oriented(path)
+        .ok()
+        .or_else(|| run_thumbnailer(path, mime_types, size.pixels()));
+    let Some(image) = image else {
+        // an empty image carrying the URI and MTime marks the failure
+        let fail = cache_dir.join(FAIL_DIR).join(&name);
+        write_thumbnail(&fail, (1, 1), &[0; 4], &uri, mtime);
+        return None;
+    };
+    let pixels = size.pixels();
+    // images smaller than the thumbnail are kept at their size
+    let image = if image.width() > pixels || image.height() > pixels {
+        image.thumbnail(pixels, pixels)
+    } else {
+        image
+    };
+    let image = image.into_rgba8();
+    let thumbnail = cache_dir.join(size.dir()).join(&name);
+    write_thumbnail(&thumbnail, image.dimensions(), image.as_raw(), &uri, mtime)?;
+    Some(thumbnail)
+}
+
+/// The thumbnail of `path`, generated on the thumbnail pool if needed.
+pub async fn thumbnail(
+    path: PathBuf,
+    mime_types: Vec<Mime>,
+    size: ThumbnailSize,
+) -> (PathBuf, Option<PathBuf>) {
+    let (sender, receiver) = tokio::sync::oneshot::channel();
+    POOL.spawn(move || {
+        let cache_dir = thumbnails_dir();
+        // thumbnails are named after the URI, which other programs make of
+        // the absolute path
+        let thumbnail = path.canonicalize().ok().and_then(|original| {
+            match cached_thumbnail(&cache_dir, &original, size) {
+                Some(thumbnail) => Some(thumbnail),
+                None if has_failed(&cache_dir, &original) => None,
+                None => generate(&cache_dir, &original, &mime_types, size),
+            }
+        });
+        let _ = sender.send((path, thumbnail));
+    });
+    receiver.await.unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn writes_thumbnails_to_the_cache() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache_dir = dir.path().join("thumbnails");
+        let image = dir.path().join("image.png");
+        image::RgbaImage::new(400, 200).save(&image).unwrap();
+        assert!(cached_thumbnail(&cache_dir, &image, ThumbnailSize::Normal).is_none());
+
+        let thumbnail = generate(
+            &cache_dir,
+            &image,
+            &[mime::IMAGE_PNG],
+            ThumbnailSize::Normal,
+        )
+        .unwrap();
+        let name = thumbnail_name(&path_to_uri(&image));
+        assert_eq!(thumbnail, cache_dir.join("normal").join(name));
+        assert_eq!(image::image_dimensions(&thumbnail).unwrap(), (128, 64));
+        assert_eq!(
+            cached_thumbnail(&cache_dir, &image, ThumbnailSize::Normal),
+            Some(thumbnail)
+        );
+        assert!(!has_failed(&cache_dir, &image));
+        // a large one has to be made on its own
+        assert!(cached_thumbnail(&cache_dir, &image, ThumbnailSize::Large).is_none());
+    }
+
+    #[test]
+    fn marks_failures() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache_dir = dir.path().join("thumbnails");
+        let broken = dir.path().join("broken.png");
+        fs::write(&broken, b"not a png").unwrap();
+        assert!(generate(
+            &cache_dir,
+            &broken,
+            &[mime::IMAGE_PNG],
+            ThumbnailSize::Normal
+        )
+        .is_none());
+        let name = thumbnail_name(&path_to_uri(&broken));
+        assert!(cache_dir.join(FAIL_DIR).join(name).is_file());
+        assert!(has_failed(&cache_dir, &broken));
+        assert!(cached_thumbnail(&cache_dir, &broken, ThumbnailSize::Normal).is_none());
+    }
+
+    #[test]
+    fn invalidates_changed_originals() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache_dir = dir.path().join("thumbnails");
+        let image = dir.path().join("image.png");
+        image::RgbaImage::new(4, 4).save(&image).unwrap();
+        generate(
+            &cache_dir,
+            &image,
+            &[mime::IMAGE_PNG],
+            ThumbnailSize::Normal,
+        )
+        .unwrap();
+        let later = std::time::SystemTime::now() + std::time::Duration::from_secs(10);
+        File::options()
+            .write(true)
+            .open(&image)
+            .unwrap()
+            .set_modified(later)
+            .unwrap();
+        assert!(cached_thumbnail(&cache_dir, &image, ThumbnailSize::Normal).is_none());
+    }
+}
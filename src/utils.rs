@@ -1,33 +1,57 @@
+use std::env;
 use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
 
-use glob::glob;
-
-pub fn get_icon(theme: &str, icon: &str) -> Option<String> {
-    let Ok(mut globfrometheme) =
-        glob(format!("/usr/share/icons/{theme}/**/**/{icon}.svg").as_str())
-    else {
-        return None;
-    };
-    if let Some(Ok(icon)) = globfrometheme.next() {
-        return Some(
-            fs::canonicalize(icon)
-                .unwrap()
-                .as_os_str()
-                .to_string_lossy()
-                .to_string(),
-        );
-    }
-    let Ok(mut globfrometheme) = glob(format!("/usr/share/icons/**/**/{icon}.svg").as_str()) else {
-        return None;
-    };
-    if let Some(Ok(icon)) = globfrometheme.next() {
-        return Some(
-            fs::canonicalize(icon)
-                .unwrap()
-                .as_os_str()
-                .to_string_lossy()
-                .to_string(),
-        );
+use users::os::unix::UserExt;
+
+pub fn home_dir() -> PathBuf {
+    if let Some(home) = env::var_os("HOME").filter(|home| !home.is_empty()) {
+        return PathBuf::from(home);
     }
-    None
+    users::get_user_by_uid(users::get_current_uid())
+        .map(|user| user.home_dir().to_path_buf())
+        .unwrap_or_else(|| PathBuf::from("/"))
+}
+
+fn xdg_dir(key: &str, fallback: &str) -> PathBuf {
+    env::var_os(key)
+        .map(PathBuf::from)
+        .filter(|dir| dir.is_absolute())
+        .unwrap_or_else(|| home_dir().join(fallback))
+}
+
+pub fn xdg_config_home() -> PathBuf {
+    xdg_dir("XDG_CONFIG_HOME", ".config")
+}
+
+pub fn xdg_cache_home() -> PathBuf {
+    xdg_dir("XDG_CACHE_HOME", ".cache")
+}
+
+pub fn xdg_data_dirs() -> Vec<PathBuf> {
+    let dirs = env::var("XDG_DATA_DIRS")
+        .ok()
+        .filter(|dirs| !dirs.is_empty())
+        .unwrap_or("/usr/local/share:/usr/share".to_string());
+    dirs.split(':')
+        .map(PathBuf::from)
+        .filter(|dir| dir.is_absolute())
+        .collect()
+}
+
+pub fn xdg_data_home() -> PathBuf {
+    xdg_dir("XDG_DATA_HOME", ".local/share")
+}
+
+/// Writes through a temporary file in the same directory and renames it over
+/// `path`, so readers never see a half written file.
+pub fn write_atomic(path: &Path, content: &[u8]) -> io::Result<()> {
+    let parent = path.parent().unwrap_or(Path::new("/"));
+    fs::create_dir_all(parent)?;
+    let mut temp = tempfile::NamedTempFile::new_in(parent)?;
+    temp.write_all(content)?;
+    temp.as_file().sync_all()?;
+    temp.persist(path).map_err(|e| e.error)?;
+    Ok(())
 }
@@ -0,0 +1,135 @@
+use std::collections::HashMap;
+
+/// Far more than any icon, the header of a broken or hostile file may claim
+/// any size.
+const MAX_PIXELS: usize = 4096 * 4096;
+
+/// Decodes an XPM image, the C source format some old icons still use, into
+/// its width, height and RGBA pixels.
+///
+/// Only the `c` (color) key of each color is read, as hex or `None`; named
+/// X11 colors other than black and white come out black.
+pub fn decode(source: &str) -> Option<(u32, u32, Vec<u8>)> {
+    // every line of the image is a C string literal
+    let mut strings = source.split('"').skip(1).step_by(2);
+    let mut header = strings.next()?.split_whitespace();
+    let width: usize = header.next()?.parse().ok()?;
+    let height: usize = header.next()?.parse().ok()?;
+    let colors: usize = header.next()?.parse().ok()?;
+    let chars_per_pixel: usize = header.next()?.parse().ok()?;
+    let pixel_count = width.checked_mul(height)?;
+    if chars_per_pixel == 0 || pixel_count > MAX_PIXELS {
+        return None;
+    }
+    // a line of pixels has to fit in memory as well
+    width.checked_mul(chars_per_pixel)?;
+    let mut palette: HashMap<&str, [u8; 4]> = HashMap::with_capacity(colors.min(MAX_PIXELS));
+    for _ in 0..colors {
+        let line = strings.next()?;
+        let key = line.get(..chars_per_pixel)?;
+        let mut specs = line[chars_per_pixel..].split_whitespace();
+        let mut color = [0, 0, 0, 255];
+        while let Some(kind) = specs.next() {
+            let value = specs.next()?;
+            if kind == "c" {
+                color = parse_color(value);
+                break;
+            }
+        }
+        palette.insert(key, color);
+    }
+    let mut pixels = Vec::with_capacity(pixel_count * 4);
+    for _ in 0..height {
+        let line = strings.next()?;
+        for x in 0..width {
+            let key = line.get(x * chars_per_pixel..(x + 1) * chars_per_pixel)?;
+            pixels.extend_from_slice(palette.get(key).unwrap_or(&[0, 0, 0, 0]));
+        }
+    }
+    Some((width as u32, height as u32, pixels))
+}
+
+fn parse_color(value: &str) -> [u8; 4] {
+    if value.eq_ignore_ascii_case("none") {
+        return [0, 0, 0, 0];
+    }
+    if value.eq_ignore_ascii_case("white") {
+        return [255, 255, 255, 255];
+    }
+    let Some(hex) = value.strip_prefix('#') else {
+        return [0, 0, 0, 255];
+    };
+    // #rgb, #rrggbb and #rrrrggggbbbb, keeping the high byte of each channel
+    let digits = hex.len() / 3;
+    if digits == 0 || hex.len() % 3 != 0 {
+        return [0, 0, 0, 255];
+    }
+    let channel = |index: usize| {
+        let part = hex.get(index * digits..(index + 1) * digits).unwrap_or("0");
+        let high = part.get(..part.len().min(2)).unwrap_or("0");
+        let value = u8::from_str_radix(high, 16).unwrap_or(0);
+        if digits == 1 {
+            value * 17
+        } else {
+            value
+        }
+    };
+    [channel(0), channel(1), channel(2), 255]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const IMAGE: &str = r##"/* XPM */
+static char *icon[] = {
+"3 2 3 1",
+"  c None",
+". c #ff0000",
+"X c #00000000ffff",
+" .X",
+"X. "
+};"##;
+
+    #[test]
+    fn decodes_images() {
+        let (width, height, pixels) = decode(IMAGE).unwrap();
+        assert_eq!((width, height), (3, 2));
+        assert_eq!(
+            pixels,
+            [
+                [0, 0, 0, 0],
+                [255, 0, 0, 255],
+                [0, 0, 255, 255],
+                [0, 0, 255, 255],
+                [255, 0, 0, 255],
+                [0, 0, 0, 0],
+            ]
+            .concat()
+        );
+    }
+
+    #[test]
+    fn parses_colors() {
+        assert_eq!(parse_color("#f80"), [255, 136, 0, 255]);
+        assert_eq!(parse_color("#123456"), [0x12, 0x34, 0x56, 255]);
+        assert_eq!(parse_color("#ffff00008000"), [255, 0, 128, 255]);
+        assert_eq!(parse_color("White"), [255, 255, 255, 255]);
+        assert_eq!(parse_color("none"), [0, 0, 0, 0]);
+        // broken channels come out 0 instead of panicking
+        assert_eq!(parse_color("#é1"), [0, 0, 17, 255]);
+        assert_eq!(parse_color("#ééé"), [0, 0, 0, 255]);
+        assert_eq!(parse_color("#"), [0, 0, 0, 255]);
+    }
+
+    #[test]
+    fn rejects_broken_headers() {
+        assert!(decode(r#""99999999999999999999 2 1 1""#).is_none());
+        assert!(decode(r#""4294967296 4294967296 1 1""#).is_none());
+        assert!(decode(r#""100000 100000 1 1""#).is_none());
+        assert!(decode(r#""2 2 1 0""#).is_none());
+        assert!(decode(r#""2 2 99999999999 1", ". c #000""#).is_none());
+        // fewer pixels than the header claims
+        assert!(decode(r#""2 2 1 1", ". c #000", "..", ".""#).is_none());
+    }
+}